@@ -0,0 +1,1099 @@
+// Escrita dos backups nos formatos suportados (zip, tar.zst, cópia em pasta e
+// snapshot no repositório).
+//
+// Um mundo sozinho fica com o conteúdo na raiz do backup. Num grupo (e sempre nos
+// backups em pasta e no repositório) cada pasta vai com o próprio nome e o manifesto
+// `auto-mine-backup.json` lista as pastas a restaurar. No zip, com mais de uma
+// thread, os arquivos são comprimidos em paralelo (em memória, ou num arquivo
+// temporário quando são grandes) e copiados já comprimidos, na ordem em que foram
+// encontrados.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::Store;
+use walkdir::WalkDir;
+use zip::result::ZipResult;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::enums::{ArchiveFormat, CompressionMethod};
+use crate::integrity::{self, FileHash, HashingReader, BUFFER_SIZE};
+use crate::repository::{self, RepositoryWriter};
+use crate::snapshot;
+use crate::utils::log_to_file_and_emit;
+use crate::world_group::{self, WorldGroup};
+
+pub const FILES_TO_IGNORE: [&str; 2] = ["session.lock", "level.dat_old"];
+
+// Arquivos maiores que isso são comprimidos num arquivo temporário, não na memória
+const IN_MEMORY_LIMIT: u64 = 32 * 1024 * 1024;
+// Threads usadas no modo gentil, enquanto o jogo ou um servidor está aberto
+const GENTLE_THREADS: usize = 1;
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+// Arquivos que já são comprimidos: regiões e chunks grandes (.mca, .mcc), NBT em
+// gzip (.dat), imagens e as tabelas do LevelDB do Bedrock (.ldb)
+const DEFAULT_STORED_EXTENSIONS: [&str; 5] = ["mca", "mcc", "dat", "png", "ldb"];
+
+pub struct Entry {
+    pub path: PathBuf,
+    // Nome no backup, sempre separado por `/`
+    pub name: String,
+    pub is_dir: bool,
+}
+
+// Entrada do zip já comprimida por uma thread, esperando a escrita
+enum Compressed {
+    Directory,
+    Memory(ZipArchive<Cursor<Vec<u8>>>),
+    TempFile(ZipArchive<File>, PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub struct ArchiveSettings {
+    pub format: ArchiveFormat,
+    pub threads: usize,
+    pub zstd_level: i32,
+    pub zstd_long_distance: bool,
+    pub zip_method: CompressionMethod,
+    // None usa o nível padrão do método
+    pub zip_level: Option<i64>,
+    // Extensões guardadas no zip sem recomprimir
+    pub stored_extensions: Vec<String>,
+}
+
+impl ArchiveSettings {
+    // Lê `archive_format`, `zstd_level`, `zstd_long_distance` e as threads de
+    // compressão: `compression_threads` (padrão: um por núcleo) ou uma só, com
+    // `gentle_compression` (padrão: ativado), enquanto o jogo está aberto.
+    //
+    // O método e o nível do zip vêm de `compression_method` e `compression_level`,
+    // ou de `world_compression.<nome>` (`method` e `level`) para o mundo `world_name`.
+    // Com `store_compressed_files` (padrão: ativado), os arquivos com as extensões de
+    // `stored_extensions` vão sem recomprimir.
+    pub fn from_store(
+        store: &Arc<Store<Wry>>,
+        world_name: &str,
+        game_running: bool,
+    ) -> ArchiveSettings {
+        let gentle = store
+            .get("gentle_compression")
+            .and_then(|value| value.as_bool())
+            .unwrap_or(true);
+        let threads = if gentle && game_running {
+            GENTLE_THREADS
+        } else {
+            store
+                .get("compression_threads")
+                .and_then(|value| value.as_u64())
+                .map(|threads| threads as usize)
+                .filter(|threads| *threads > 0)
+                .unwrap_or_else(|| {
+                    thread::available_parallelism()
+                        .map(|threads| threads.get())
+                        .unwrap_or(1)
+                })
+        };
+
+        let world = store
+            .get("world_compression")
+            .and_then(|worlds| worlds.get(world_name).cloned());
+        let setting = |world_key: &str, global_key: &str| {
+            world
+                .as_ref()
+                .and_then(|world| world.get(world_key).cloned())
+                .or_else(|| store.get(global_key))
+        };
+        let store_compressed = store
+            .get("store_compressed_files")
+            .and_then(|value| value.as_bool())
+            .unwrap_or(true);
+        let stored_extensions = if store_compressed {
+            store
+                .get("stored_extensions")
+                .and_then(|values| {
+                    values.as_array().map(|values| {
+                        values
+                            .iter()
+                            .filter_map(|value| value.as_str().map(str::to_lowercase))
+                            .collect()
+                    })
+                })
+                .unwrap_or_else(|| {
+                    DEFAULT_STORED_EXTENSIONS
+                        .iter()
+                        .map(|extension| extension.to_string())
+                        .collect()
+                })
+        } else {
+            Vec::new()
+        };
+
+        ArchiveSettings {
+            format: store
+                .get("archive_format")
+                .and_then(|value| value.as_str().map(ArchiveFormat::from_name))
+                .unwrap_or(ArchiveFormat::Zip),
+            threads,
+            zstd_level: store
+                .get("zstd_level")
+                .and_then(|value| value.as_i64())
+                .map(|level| level.clamp(-7, 22) as i32)
+                .unwrap_or(DEFAULT_ZSTD_LEVEL),
+            zstd_long_distance: store
+                .get("zstd_long_distance")
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false),
+            zip_method: setting("method", "compression_method")
+                .and_then(|value| value.as_str().map(CompressionMethod::from_name))
+                .unwrap_or(CompressionMethod::Deflate),
+            zip_level: setting("level", "compression_level").and_then(|value| value.as_i64()),
+            stored_extensions,
+        }
+    }
+}
+
+// Sufixo do backup enquanto está sendo escrito. Só depois de completo e gravado em
+// disco ele é renomeado para o nome final, então um backup interrompido nunca é
+// confundido com um válido.
+pub const PARTIAL_SUFFIX: &str = ".partial";
+
+pub fn partial_path(dst: &Path) -> PathBuf {
+    let mut name = dst.file_name().unwrap_or_default().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    dst.with_file_name(name)
+}
+
+// Um snapshot incompleto pode já ter sido contado no índice do repositório. Retorna o
+// erro de leitura de um snapshot ilegível, apagado sem ser lido.
+fn remove_partial(partial: &Path) -> io::Result<Option<io::Error>> {
+    let snapshot = ArchiveFormat::Repository
+        .extension()
+        .is_some_and(|extension| {
+            partial
+                .to_string_lossy()
+                .ends_with(&format!(".{}{}", extension, PARTIAL_SUFFIX))
+        });
+    if snapshot && partial.is_file() {
+        repository::release(partial)
+    } else {
+        remove_path(partial).map(|()| None)
+    }
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+// Garante que o arquivo está gravado em disco. No Windows, precisa de acesso de
+// escrita.
+fn sync_file(path: &Path) -> io::Result<()> {
+    fs::OpenOptions::new().write(true).open(path)?.sync_all()
+}
+
+// Grava em disco a entrada da pasta (o rename do backup). O Windows não abre pastas
+// assim e já grava os metadados de forma síncrona.
+fn sync_dir(dir: &Path) {
+    if cfg!(unix) {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+}
+
+// Apaga, em cada pasta `backup_<nome>` de `backup_root`, os backups `.partial`, os
+// arquivos temporários da compressão, as cópias do mundo e os pedaços do repositório
+// que sobraram de um backup interrompido. Cada caminho apagado vem com o erro de
+// leitura, se era um snapshot ilegível.
+pub fn remove_partials(backup_root: &Path) -> Vec<(PathBuf, Option<io::Error>)> {
+    let mut removed = Vec::new();
+    let Ok(folders) = fs::read_dir(backup_root) else {
+        return removed;
+    };
+    for folder in folders.filter_map(|folder| folder.ok()) {
+        if !folder.path().is_dir() {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(folder.path()) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let leftover = name.ends_with(PARTIAL_SUFFIX)
+                || (name.starts_with('.') && name.ends_with(".tmp"))
+                || name.starts_with(snapshot::SNAPSHOT_PREFIX);
+            if !leftover {
+                continue;
+            }
+            if let Ok(unreadable) = remove_partial(&entry.path()) {
+                removed.push((entry.path(), unreadable));
+            }
+        }
+        removed.extend(
+            repository::remove_orphans(&folder.path().join(repository::REPOSITORY_DIR))
+                .into_iter()
+                .map(|chunk| (chunk, None)),
+        );
+    }
+    removed
+}
+
+// Resultado de um backup escrito e conferido
+#[derive(Debug, Clone, Default)]
+pub struct BackupReport {
+    pub files: usize,
+    // Arquivos que não puderam ser lidos e ficaram de fora
+    pub skipped: usize,
+}
+
+// Destino de um backup. Cada formato implementa a escrita das entradas; falhas numa
+// entrada são registradas no log e a entrada fica de fora. Cada arquivo adicionado
+// retorna o seu SHA-256, calculado sobre o que foi gravado.
+pub trait ArchiveWriter {
+    // Arquivo gerado pelo app (manifesto, lista de hashes) na raiz do backup
+    fn add_data(&mut self, name: &str, data: &[u8]) -> io::Result<()>;
+    fn add_directory(&mut self, entry: &Entry) -> io::Result<()>;
+    fn add_file(&mut self, entry: &Entry) -> io::Result<FileHash>;
+
+    fn add_entries(&mut self, entries: &[Entry], log: &dyn Fn(String)) -> Vec<FileHash> {
+        add_each(self, entries, log)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+fn add_each<W: ArchiveWriter + ?Sized>(
+    writer: &mut W,
+    entries: &[Entry],
+    log: &dyn Fn(String),
+) -> Vec<FileHash> {
+    let mut hashes = Vec::new();
+    for entry in entries {
+        let added = if entry.is_dir {
+            writer.add_directory(entry)
+        } else {
+            writer.add_file(entry).map(|hash| hashes.push(hash))
+        };
+        if let Err(e) = added {
+            log(format!(
+                "[add_entries] Falha ao adicionar ao backup: {:?} - {}",
+                entry.path, e
+            ));
+        }
+    }
+    hashes
+}
+
+fn open_writer(settings: &ArchiveSettings, dst: &Path) -> io::Result<Box<dyn ArchiveWriter>> {
+    Ok(match settings.format {
+        ArchiveFormat::Zip | ArchiveFormat::McWorld => {
+            Box::new(ZipBackupWriter::create(dst, settings)?)
+        }
+        ArchiveFormat::TarZst => Box::new(TarZstWriter::create(dst, settings)?),
+        ArchiveFormat::Folder => Box::new(FolderWriter::create(dst)?),
+        ArchiveFormat::Repository => Box::new(RepositoryWriter::create(dst, settings)?),
+    })
+}
+
+// Grava as pastas do grupo em `dst` no formato das configurações. O backup é escrito
+// em `<dst>.partial`, conferido e renomeado no fim; se a conferência falhar, ele é
+// apagado. Todo o trabalho com arquivos roda numa
+// thread de bloqueio separada, para não travar o runtime (e a verificação do jogo)
+// durante backups grandes.
+pub async fn write_backup(
+    group: &WorldGroup,
+    dst: &Path,
+    settings: &ArchiveSettings,
+    app_handle: &AppHandle,
+) -> io::Result<BackupReport> {
+    let group = group.clone();
+    let dst = dst.to_path_buf();
+    let settings = settings.clone();
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        write_world_group(&group, &dst, &settings, &|message| {
+            log_to_file_and_emit(&app_handle, message)
+        })
+    })
+    .await
+    .map_err(|e| io::Error::other(format!("tarefa de compactação falhou: {}", e)))?
+}
+
+// Parte síncrona de `write_backup`; as mensagens vão para `log`
+pub fn write_world_group(
+    group: &WorldGroup,
+    dst: &Path,
+    settings: &ArchiveSettings,
+    log: &dyn Fn(String),
+) -> io::Result<BackupReport> {
+    let partial = partial_path(dst);
+    let written = write_entries(group, &partial, settings, log)
+        .and_then(|report| fs::rename(&partial, dst).map(|_| report));
+    match written {
+        Ok(_) => {
+            if let Some(parent) = dst.parent() {
+                sync_dir(parent);
+            }
+        }
+        Err(_) => {
+            let _ = remove_partial(&partial);
+        }
+    }
+    written
+}
+
+fn write_entries(
+    group: &WorldGroup,
+    dst: &Path,
+    settings: &ArchiveSettings,
+    log: &dyn Fn(String),
+) -> io::Result<BackupReport> {
+    let mut writer = open_writer(settings, dst)?;
+
+    // Um mundo só vai na raiz do zip e do tar.zst (o `.mcworld` precisa disso); grupos,
+    // pastas e o repositório têm uma pasta por mundo ou dimensão
+    let at_root = !group.is_group()
+        && !matches!(
+            settings.format,
+            ArchiveFormat::Folder | ArchiveFormat::Repository
+        );
+    let manifest = serde_json::to_vec_pretty(&group.manifest(at_root)).unwrap_or_default();
+    writer.add_data(world_group::MANIFEST_NAME, &manifest)?;
+
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    if at_root {
+        if let Some(dir) = group.dirs().first() {
+            skipped += collect_entries(dir, None, &mut entries, log);
+        }
+    } else {
+        for (folder, dir) in group.folders.iter().zip(group.dirs()) {
+            skipped += collect_entries(&dir, Some(folder), &mut entries, log);
+        }
+    }
+
+    let hashes = writer.add_entries(&entries, log);
+    let files = entries.iter().filter(|entry| !entry.is_dir).count();
+    skipped += files - hashes.len();
+    let hashes_json = serde_json::to_vec_pretty(&hashes).map_err(io::Error::other)?;
+    writer.add_data(integrity::HASHES_NAME, &hashes_json)?;
+    writer.finish()?;
+
+    integrity::verify(settings.format, dst, &hashes).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("o backup não passou na conferência: {}", e),
+        )
+    })?;
+    Ok(BackupReport {
+        files: hashes.len(),
+        skipped,
+    })
+}
+
+// Lista o conteúdo de `src_dir`, dentro da pasta `prefix` quando informada, em ordem
+// alfabética e com cada pasta antes do conteúdo dela. Retorna quantas entradas não
+// puderam ser acessadas.
+fn collect_entries(
+    src_dir: &Path,
+    prefix: Option<&str>,
+    entries: &mut Vec<Entry>,
+    log: &dyn Fn(String),
+) -> usize {
+    let mut failed = 0;
+    for entry in WalkDir::new(src_dir).sort_by_file_name() {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                log(format!("[collect_entries] Falha ao acessar entrada: {}", e));
+                failed += 1;
+                continue;
+            }
+        };
+        let path = entry.path();
+        let name = prefix
+            .into_iter()
+            .map(String::from)
+            .chain(
+                path.strip_prefix(src_dir)
+                    .unwrap()
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy().to_string()),
+            )
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if path.is_file() {
+            // Ignorar arquivos da lista FILES_TO_IGNORE
+            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
+                if FILES_TO_IGNORE.contains(&file_name) {
+                    log(format!("[collect_entries] Ignorando arquivo: {:?}", path));
+                    continue;
+                }
+            }
+            entries.push(Entry {
+                path: path.to_path_buf(),
+                name,
+                is_dir: false,
+            });
+        } else if !name.is_empty() {
+            entries.push(Entry {
+                path: path.to_path_buf(),
+                name,
+                is_dir: true,
+            });
+        }
+    }
+    failed
+}
+
+// Cópia simples das pastas, sem compressão
+struct FolderWriter {
+    root: PathBuf,
+}
+
+impl FolderWriter {
+    fn create(dst: &Path) -> io::Result<FolderWriter> {
+        fs::create_dir_all(dst)?;
+        Ok(FolderWriter {
+            root: dst.to_path_buf(),
+        })
+    }
+
+    fn target(&self, entry: &Entry) -> PathBuf {
+        entry
+            .name
+            .split('/')
+            .fold(self.root.clone(), |path, part| path.join(part))
+    }
+}
+
+impl ArchiveWriter for FolderWriter {
+    fn add_data(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.root.join(name);
+        fs::write(&path, data)?;
+        sync_file(&path)
+    }
+
+    fn add_directory(&mut self, entry: &Entry) -> io::Result<()> {
+        fs::create_dir_all(self.target(entry))
+    }
+
+    fn add_file(&mut self, entry: &Entry) -> io::Result<FileHash> {
+        let target = self.target(entry);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let source = File::open(&entry.path)?;
+        let mut reader = HashingReader::new(BufReader::with_capacity(BUFFER_SIZE, source));
+        let mut copy = File::create(&target)?;
+        io::copy(&mut reader, &mut copy)?;
+        copy.sync_all()?;
+        Ok(reader.finish(&entry.name))
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        sync_dir(&self.root);
+        Ok(())
+    }
+}
+
+// Tar comprimido com zstd. A compressão usa as threads configuradas e, com
+// `zstd_long_distance`, procura repetições numa janela maior (melhor para mundos com
+// muitos chunks parecidos).
+struct TarZstWriter {
+    builder: tar::Builder<zstd::Encoder<'static, BufWriter<File>>>,
+    // Um arquivo que mudou de tamanho durante a cópia deixa o tar inválido
+    broken: Option<io::Error>,
+}
+
+impl TarZstWriter {
+    fn create(dst: &Path, settings: &ArchiveSettings) -> io::Result<TarZstWriter> {
+        let file = BufWriter::with_capacity(BUFFER_SIZE, File::create(dst)?);
+        let mut encoder = zstd::Encoder::new(file, settings.zstd_level)?;
+        encoder.include_checksum(true)?;
+        if settings.threads > 1 {
+            encoder.multithread(settings.threads as u32)?;
+        }
+        if settings.zstd_long_distance {
+            encoder.long_distance_matching(true)?;
+        }
+        let mut builder = tar::Builder::new(encoder);
+        builder.mode(tar::HeaderMode::Complete);
+        Ok(TarZstWriter {
+            builder,
+            broken: None,
+        })
+    }
+
+    fn header(size: u64) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(size);
+        header.set_mode(0o644);
+        header.set_mtime(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|now| now.as_secs())
+                .unwrap_or_default(),
+        );
+        header
+    }
+}
+
+impl ArchiveWriter for TarZstWriter {
+    fn add_data(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut header = TarZstWriter::header(data.len() as u64);
+        self.builder.append_data(&mut header, name, data)
+    }
+
+    fn add_directory(&mut self, entry: &Entry) -> io::Result<()> {
+        self.builder.append_dir(&entry.name, &entry.path)
+    }
+
+    fn add_file(&mut self, entry: &Entry) -> io::Result<FileHash> {
+        let file = File::open(&entry.path)?;
+        let metadata = file.metadata()?;
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata_in_mode(&metadata, tar::HeaderMode::Complete);
+        // Só o tamanho lido agora entra no tar, mesmo se o arquivo crescer
+        let mut reader =
+            HashingReader::new(BufReader::with_capacity(BUFFER_SIZE, file)).take(metadata.len());
+        let appended = self
+            .builder
+            .append_data(&mut header, &entry.name, &mut reader);
+        if appended.is_err() || reader.limit() > 0 {
+            let e = appended.err().unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("{:?} diminuiu durante a cópia", entry.path),
+                )
+            });
+            self.broken = Some(io::Error::new(e.kind(), e.to_string()));
+            return Err(e);
+        }
+        Ok(reader.into_inner().finish(&entry.name))
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        if let Some(e) = self.broken {
+            return Err(e);
+        }
+        let file = self.builder.into_inner()?.finish()?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()
+    }
+}
+
+// Opções das entradas do zip
+struct ZipOptions {
+    base: SimpleFileOptions,
+    stored_extensions: Vec<String>,
+}
+
+impl ZipOptions {
+    fn new(settings: &ArchiveSettings) -> ZipOptions {
+        // Faixas de nível aceitas por cada método
+        let (method, level) = match settings.zip_method {
+            CompressionMethod::Stored => (zip::CompressionMethod::Stored, None),
+            CompressionMethod::Deflate => (
+                zip::CompressionMethod::Deflated,
+                settings.zip_level.map(|level| level.clamp(0, 9)),
+            ),
+            CompressionMethod::Zstd => (
+                zip::CompressionMethod::Zstd,
+                settings.zip_level.map(|level| level.clamp(-7, 22)),
+            ),
+            CompressionMethod::Bzip2 => (
+                zip::CompressionMethod::Bzip2,
+                settings.zip_level.map(|level| level.clamp(1, 9)),
+            ),
+        };
+        ZipOptions {
+            base: SimpleFileOptions::default()
+                .compression_method(method)
+                .compression_level(level)
+                .unix_permissions(0o755),
+            stored_extensions: settings.stored_extensions.clone(),
+        }
+    }
+
+    // Se o arquivo já é comprimido e vai sem recomprimir
+    fn is_stored(&self, path: &Path) -> bool {
+        path.extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .is_some_and(|extension| self.stored_extensions.contains(&extension))
+    }
+
+    // Arquivos já comprimidos vão sem recomprimir, e os de 4 GB ou mais precisam das
+    // extensões Zip64
+    fn for_file(&self, entry: &Entry, file: &File) -> SimpleFileOptions {
+        let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        let mut options = self.base.large_file(size >= u32::MAX as u64);
+        if self.is_stored(&entry.path) {
+            options = options
+                .compression_method(zip::CompressionMethod::Stored)
+                .compression_level(None);
+        }
+        options
+    }
+}
+
+// Zip comprimido em paralelo quando há mais de uma thread
+struct ZipBackupWriter {
+    zip: ZipWriter<BufWriter<File>>,
+    options: ZipOptions,
+    threads: usize,
+    dst: PathBuf,
+}
+
+impl ZipBackupWriter {
+    fn create(dst: &Path, settings: &ArchiveSettings) -> io::Result<ZipBackupWriter> {
+        let file = File::create(dst)?;
+        Ok(ZipBackupWriter {
+            zip: ZipWriter::new(BufWriter::with_capacity(BUFFER_SIZE, file)),
+            options: ZipOptions::new(settings),
+            threads: settings.threads,
+            dst: dst.to_path_buf(),
+        })
+    }
+}
+
+impl ArchiveWriter for ZipBackupWriter {
+    fn add_data(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.zip.start_file(name, self.options.base)?;
+        self.zip.write_all(data)
+    }
+
+    fn add_directory(&mut self, entry: &Entry) -> io::Result<()> {
+        Ok(self
+            .zip
+            .add_directory(entry.name.as_str(), self.options.base)?)
+    }
+
+    // Comprime direto no zip, em blocos
+    fn add_file(&mut self, entry: &Entry) -> io::Result<FileHash> {
+        let f = File::open(&entry.path)?;
+        self.zip
+            .start_file(entry.name.as_str(), self.options.for_file(entry, &f))?;
+        let mut reader = HashingReader::new(BufReader::with_capacity(BUFFER_SIZE, f));
+        if let Err(e) = io::copy(&mut reader, &mut self.zip) {
+            // Descarta a entrada pela metade em vez de deixar um arquivo truncado
+            self.zip.abort_file()?;
+            return Err(e);
+        }
+        Ok(reader.finish(&entry.name))
+    }
+
+    fn add_entries(&mut self, entries: &[Entry], log: &dyn Fn(String)) -> Vec<FileHash> {
+        if self.threads > 1 {
+            zip_parallel(
+                &mut self.zip,
+                entries,
+                &self.options,
+                self.threads,
+                &self.dst,
+                log,
+            )
+        } else {
+            add_each(self, entries, log)
+        }
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        let file = self.zip.finish()?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()
+    }
+}
+
+// Comprime um arquivo num zip próprio com uma única entrada, de onde ela é copiada
+// sem recomprimir
+fn compress_entry(
+    entry: &Entry,
+    options: &ZipOptions,
+    temp_path: &Path,
+) -> ZipResult<(Compressed, Option<FileHash>)> {
+    if entry.is_dir {
+        return Ok((Compressed::Directory, None));
+    }
+
+    let source = File::open(&entry.path)?;
+    let options = options.for_file(entry, &source);
+    let size = source.metadata()?.len();
+    let mut reader = HashingReader::new(BufReader::with_capacity(BUFFER_SIZE, source));
+
+    if size <= IN_MEMORY_LIMIT {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::with_capacity(size as usize / 2)));
+        zip.start_file(entry.name.as_str(), options)?;
+        io::copy(&mut reader, &mut zip)?;
+        let buffer = zip.finish()?.into_inner();
+        let archive = ZipArchive::new(Cursor::new(buffer))?;
+        return Ok((
+            Compressed::Memory(archive),
+            Some(reader.finish(&entry.name)),
+        ));
+    }
+
+    let temp = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(temp_path)?;
+    let compressed = (|| {
+        let mut zip = ZipWriter::new(BufWriter::with_capacity(BUFFER_SIZE, temp));
+        zip.start_file(entry.name.as_str(), options)?;
+        io::copy(&mut reader, &mut zip)?;
+        let temp = zip.finish()?.into_inner().map_err(|e| e.into_error())?;
+        ZipArchive::new(temp)
+    })();
+    match compressed {
+        Ok(archive) => Ok((
+            Compressed::TempFile(archive, temp_path.to_path_buf()),
+            Some(reader.finish(&entry.name)),
+        )),
+        Err(e) => {
+            let _ = fs::remove_file(temp_path);
+            Err(e)
+        }
+    }
+}
+
+fn write_compressed(
+    zip: &mut ZipWriter<BufWriter<File>>,
+    entry: &Entry,
+    compressed: Compressed,
+    options: &ZipOptions,
+) -> ZipResult<()> {
+    match compressed {
+        Compressed::Directory => zip.add_directory(entry.name.as_str(), options.base),
+        Compressed::Memory(mut archive) => zip.raw_copy_file(archive.by_index_raw(0)?),
+        Compressed::TempFile(mut archive, temp_path) => {
+            let copied = archive
+                .by_index_raw(0)
+                .and_then(|file| zip.raw_copy_file(file));
+            let _ = fs::remove_file(temp_path);
+            copied
+        }
+    }
+}
+
+// Comprime os arquivos em `threads` threads e escreve os resultados no zip na ordem
+// original. No máximo duas entradas por thread ficam prontas esperando a escrita.
+fn zip_parallel(
+    zip: &mut ZipWriter<BufWriter<File>>,
+    entries: &[Entry],
+    options: &ZipOptions,
+    threads: usize,
+    dst_file: &Path,
+    log: &dyn Fn(String),
+) -> Vec<FileHash> {
+    let temp_path = |index: usize| {
+        let file_name = dst_file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        dst_file.with_file_name(format!(".{}.{}.tmp", file_name, index))
+    };
+
+    let (job_sender, job_receiver) = mpsc::channel::<usize>();
+    let job_receiver = Mutex::new(job_receiver);
+    let (result_sender, result_receiver) =
+        mpsc::channel::<(usize, ZipResult<(Compressed, Option<FileHash>)>)>();
+    let mut hashes = Vec::new();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let result_sender = result_sender.clone();
+            let job_receiver = &job_receiver;
+            let temp_path = &temp_path;
+            scope.spawn(move || loop {
+                let job = job_receiver.lock().unwrap().recv();
+                let Ok(index) = job else {
+                    break;
+                };
+                let compressed = compress_entry(&entries[index], options, &temp_path(index));
+                if result_sender.send((index, compressed)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(result_sender);
+
+        let window = threads * 2;
+        let mut next_job = 0;
+        let mut ready = BTreeMap::new();
+        for (index, entry) in entries.iter().enumerate() {
+            while next_job < entries.len() && next_job < index + window {
+                let _ = job_sender.send(next_job);
+                next_job += 1;
+            }
+            let compressed = loop {
+                if let Some(compressed) = ready.remove(&index) {
+                    break compressed;
+                }
+                match result_receiver.recv() {
+                    Ok((done, compressed)) => {
+                        ready.insert(done, compressed);
+                    }
+                    // As threads só terminam depois de receber todas as tarefas
+                    Err(_) => {
+                        break Err(io::Error::other("threads de compressão encerradas").into())
+                    }
+                }
+            };
+
+            let written = compressed.and_then(|(compressed, hash)| {
+                write_compressed(zip, entry, compressed, options)?;
+                hashes.extend(hash);
+                Ok(())
+            });
+            if let Err(e) = written {
+                log(format!(
+                    "[zip_parallel] Falha ao adicionar ao zip: {:?} - {}",
+                    entry.path, e
+                ));
+            }
+        }
+        drop(job_sender);
+    });
+    hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "auto-mine-backup-archive-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn settings(format: ArchiveFormat, threads: usize) -> ArchiveSettings {
+        ArchiveSettings {
+            format,
+            threads,
+            zstd_level: 3,
+            zstd_long_distance: false,
+            zip_method: CompressionMethod::Deflate,
+            zip_level: None,
+            stored_extensions: vec!["mca".to_string(), "dat".to_string()],
+        }
+    }
+
+    // Mundo com regiões de tamanhos bem diferentes, para que as threads do zip
+    // terminem fora de ordem
+    fn world(dir: &Path) -> WorldGroup {
+        let world = dir.join("saves/world");
+        fs::create_dir_all(world.join("region")).unwrap();
+        fs::create_dir_all(world.join("data")).unwrap();
+        fs::write(world.join("level.dat"), b"level").unwrap();
+        fs::write(world.join("session.lock"), b"lock").unwrap();
+        fs::write(world.join("data/raids.dat"), b"raids").unwrap();
+        for index in 0..24 {
+            let size = if index % 3 == 0 {
+                512 * 1024
+            } else {
+                index * 7
+            };
+            let data: Vec<u8> = (0..size).map(|byte| (byte * index) as u8).collect();
+            fs::write(world.join(format!("region/r.{}.0.mca", index)), data).unwrap();
+        }
+        WorldGroup::single("world", &world)
+    }
+
+    fn collected(group: &WorldGroup) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        collect_entries(&group.dirs()[0], None, &mut entries, &|_| {});
+        entries
+    }
+
+    // Nomes esperados num backup de um mundo na raiz: o manifesto, as entradas na
+    // ordem em que foram listadas e a lista de hashes
+    fn expected_names(group: &WorldGroup) -> Vec<String> {
+        std::iter::once(world_group::MANIFEST_NAME.to_string())
+            .chain(collected(group).into_iter().map(|entry| entry.name))
+            .chain(std::iter::once(integrity::HASHES_NAME.to_string()))
+            .collect()
+    }
+
+    fn zip_names(path: &Path) -> Vec<String> {
+        let mut zip = ZipArchive::new(File::open(path).unwrap()).unwrap();
+        (0..zip.len())
+            .map(|index| {
+                let file = zip.by_index(index).unwrap();
+                file.name().trim_end_matches('/').to_string()
+            })
+            .collect()
+    }
+
+    fn tar_zst_names(path: &Path) -> Vec<String> {
+        let decoder = zstd::Decoder::new(File::open(path).unwrap()).unwrap();
+        let mut archive = tar::Archive::new(decoder);
+        archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                String::from_utf8_lossy(&entry.path_bytes())
+                    .trim_end_matches('/')
+                    .to_string()
+            })
+            .collect()
+    }
+
+    fn write(group: &WorldGroup, dst: &Path, settings: &ArchiveSettings) -> BackupReport {
+        let messages = RefCell::new(Vec::new());
+        let report = write_world_group(group, dst, settings, &|message| {
+            messages.borrow_mut().push(message)
+        })
+        .unwrap();
+        assert!(!partial_path(dst).exists());
+        // Só o session.lock é deixado de fora
+        assert_eq!(messages.into_inner().len(), 1);
+        report
+    }
+
+    #[test]
+    fn partial_path_appends_the_suffix() {
+        assert_eq!(
+            partial_path(Path::new("backups/backup_world_20260101_120000.tar.zst")),
+            Path::new("backups/backup_world_20260101_120000.tar.zst.partial")
+        );
+        assert_eq!(
+            partial_path(Path::new("backups/backup_world_20260101_120000")),
+            Path::new("backups/backup_world_20260101_120000.partial")
+        );
+    }
+
+    #[test]
+    fn stores_already_compressed_extensions() {
+        let options = ZipOptions::new(&settings(ArchiveFormat::Zip, 1));
+        assert!(options.is_stored(Path::new("world/region/r.0.0.mca")));
+        assert!(options.is_stored(Path::new("world/region/R.0.0.MCA")));
+        assert!(options.is_stored(Path::new("world/level.dat")));
+        assert!(!options.is_stored(Path::new("world/stats/uuid.json")));
+        assert!(!options.is_stored(Path::new("world/icon")));
+
+        let mut nothing_stored = settings(ArchiveFormat::Zip, 1);
+        nothing_stored.stored_extensions.clear();
+        assert!(!ZipOptions::new(&nothing_stored).is_stored(Path::new("world/level.dat")));
+    }
+
+    #[test]
+    fn collects_entries_in_order_with_folders_first() {
+        let dir = temp_dir("collect");
+        let world = dir.join("world");
+        fs::create_dir_all(world.join("b")).unwrap();
+        fs::create_dir_all(world.join("a/z")).unwrap();
+        fs::write(world.join("c.dat"), b"c").unwrap();
+        fs::write(world.join("a/y.dat"), b"y").unwrap();
+        fs::write(world.join("a/z/x.dat"), b"x").unwrap();
+        fs::write(world.join("session.lock"), b"lock").unwrap();
+        fs::write(world.join("level.dat_old"), b"old").unwrap();
+
+        let mut entries = Vec::new();
+        let messages = RefCell::new(Vec::new());
+        let failed = collect_entries(&world, Some("world"), &mut entries, &|message| {
+            messages.borrow_mut().push(message)
+        });
+        let names: Vec<(&str, bool)> = entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.is_dir))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("world", true),
+                ("world/a", true),
+                ("world/a/y.dat", false),
+                ("world/a/z", true),
+                ("world/a/z/x.dat", false),
+                ("world/b", true),
+                ("world/c.dat", false),
+            ]
+        );
+        assert_eq!(failed, 0);
+        assert_eq!(messages.into_inner().len(), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn zip_backends_keep_entry_order() {
+        let dir = temp_dir("zip");
+        let group = world(&dir);
+        let expected = expected_names(&group);
+        // Uma thread escreve direto; várias passam pela janela do `zip_parallel`
+        for threads in [1, 4] {
+            let dst = dir.join(format!("backup_{}.zip", threads));
+            let report = write(&group, &dst, &settings(ArchiveFormat::Zip, threads));
+            assert_eq!(report.files, 26);
+            assert_eq!(report.skipped, 0);
+            assert_eq!(zip_names(&dst), expected, "{} thread(s)", threads);
+
+            let mut zip = ZipArchive::new(File::open(&dst).unwrap()).unwrap();
+            for index in [0, 5, 23] {
+                let name = format!("region/r.{}.0.mca", index);
+                let mut contents = Vec::new();
+                zip.by_name(&name)
+                    .unwrap()
+                    .read_to_end(&mut contents)
+                    .unwrap();
+                assert_eq!(contents, fs::read(group.dirs()[0].join(&name)).unwrap());
+            }
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn tar_zst_backend_keeps_entry_order() {
+        let dir = temp_dir("tar-zst");
+        let group = world(&dir);
+        let dst = dir.join("backup.tar.zst");
+        let report = write(&group, &dst, &settings(ArchiveFormat::TarZst, 2));
+        assert_eq!(report.files, 26);
+        assert_eq!(tar_zst_names(&dst), expected_names(&group));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn folder_backend_copies_each_folder_by_name() {
+        let dir = temp_dir("folder");
+        let group = world(&dir);
+        let dst = dir.join("backup");
+        let report = write(&group, &dst, &settings(ArchiveFormat::Folder, 1));
+        assert_eq!(report.files, 26);
+
+        assert!(dst.join(world_group::MANIFEST_NAME).is_file());
+        assert!(dst.join(integrity::HASHES_NAME).is_file());
+        assert!(!dst.join("world/session.lock").exists());
+        for entry in collected(&group).iter().filter(|entry| !entry.is_dir) {
+            assert_eq!(
+                fs::read(dst.join("world").join(&entry.name)).unwrap(),
+                fs::read(&entry.path).unwrap(),
+                "{}",
+                entry.name
+            );
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
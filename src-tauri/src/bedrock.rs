@@ -0,0 +1,170 @@
+// Mundos do Minecraft Bedrock Edition.
+//
+// Cada mundo fica em `com.mojang/minecraftWorlds/<id>`, com o nome de exibição em
+// `levelname.txt` e os dados em LevelDB na pasta `db/`. O Bedrock não expõe quando o
+// mundo está aberto, então o backup é feito quando os arquivos do mundo mudaram
+// desde o último backup.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone)]
+pub struct BedrockWorld {
+    // Nome da pasta do mundo
+    pub id: String,
+    // Nome de exibição (levelname.txt)
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl BedrockWorld {
+    // Nome usado na pasta e nos arquivos de backup. Inclui o id porque vários mundos
+    // podem ter o mesmo nome de exibição ("Meu mundo" é o padrão do jogo).
+    pub fn backup_name(&self) -> String {
+        let name: String = self
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_control() || "<>:\"/\\|?*".contains(c) {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        format!("bedrock_{}_{}", name.trim(), self.id)
+    }
+
+    // Modificação mais recente de um arquivo do mundo, em segundos desde a época
+    pub fn last_modified(&self) -> Option<u64> {
+        WalkDir::new(&self.dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+            .max()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs())
+    }
+}
+
+// Pastas `minecraftWorlds` conhecidas de cada plataforma
+pub fn default_worlds_dirs() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    if cfg!(windows) {
+        // Versões da Microsoft Store (e a Preview)
+        if let Some(local) = dirs::data_local_dir() {
+            for package in [
+                "Microsoft.MinecraftUWP_8wekyb3d8bbwe",
+                "Microsoft.MinecraftWindowsBeta_8wekyb3d8bbwe",
+            ] {
+                candidates.push(
+                    local
+                        .join("Packages")
+                        .join(package)
+                        .join("LocalState")
+                        .join("games")
+                        .join("com.mojang"),
+                );
+            }
+        }
+        // Versões GDK (1.21.120+) guardam os mundos por usuário
+        if let Some(roaming) = dirs::config_dir() {
+            for edition in ["Minecraft Bedrock", "Minecraft Bedrock Preview"] {
+                let users = roaming.join(edition).join("Users");
+                if let Ok(entries) = fs::read_dir(&users) {
+                    candidates.extend(
+                        entries
+                            .filter_map(|entry| entry.ok())
+                            .map(|entry| entry.path().join("games").join("com.mojang")),
+                    );
+                }
+            }
+        }
+    } else if let Some(home) = dirs::home_dir() {
+        // mcpelauncher (instalado ou via Flatpak)
+        candidates.push(
+            home.join(".local")
+                .join("share")
+                .join("mcpelauncher")
+                .join("games")
+                .join("com.mojang"),
+        );
+        candidates.push(
+            home.join(".var")
+                .join("app")
+                .join("io.mrarm.mcpelauncher")
+                .join("data")
+                .join("mcpelauncher")
+                .join("games")
+                .join("com.mojang"),
+        );
+    }
+
+    candidates
+        .into_iter()
+        .map(|com_mojang| com_mojang.join("minecraftWorlds"))
+        .collect()
+}
+
+// Aceita tanto a pasta `com.mojang` quanto a própria `minecraftWorlds`
+fn worlds_dir_of(dir: &Path) -> Option<PathBuf> {
+    let worlds = dir.join("minecraftWorlds");
+    if worlds.is_dir() {
+        return Some(worlds);
+    }
+    if dir
+        .file_name()
+        .is_some_and(|name| name == "minecraftWorlds")
+        && dir.is_dir()
+    {
+        return Some(dir.to_path_buf());
+    }
+    None
+}
+
+fn read_world(world_dir: &Path) -> Option<BedrockWorld> {
+    if !world_dir.join("db").is_dir() && !world_dir.join("level.dat").is_file() {
+        return None;
+    }
+    let id = world_dir.file_name()?.to_string_lossy().to_string();
+    let name = fs::read_to_string(world_dir.join("levelname.txt"))
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| id.clone());
+    Some(BedrockWorld {
+        id,
+        name,
+        dir: world_dir.to_path_buf(),
+    })
+}
+
+// Lista os mundos dos locais padrão e das pastas em `extra_dirs` (`bedrock_dirs`)
+pub fn discover_worlds(extra_dirs: &[PathBuf]) -> Vec<BedrockWorld> {
+    let mut worlds: Vec<BedrockWorld> = Vec::new();
+    let worlds_dirs = extra_dirs
+        .iter()
+        .filter_map(|dir| worlds_dir_of(dir))
+        .chain(default_worlds_dirs().into_iter().filter(|dir| dir.is_dir()));
+
+    for worlds_dir in worlds_dirs {
+        let Ok(entries) = fs::read_dir(&worlds_dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if let Some(world) = read_world(&entry.path()) {
+                if !worlds.iter().any(|known| known.dir == world.dir) {
+                    worlds.push(world);
+                }
+            }
+        }
+    }
+
+    worlds.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+    worlds
+}
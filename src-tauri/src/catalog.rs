@@ -0,0 +1,151 @@
+// Registro dos backups de cada pasta `backup_<nome>`, no arquivo `backups.json`.
+//
+// Cada registro guarda o nome do backup (arquivo ou pasta) e o formato em que foi
+// gerado, para que o limite de backups e a restauração tratem cada um do jeito
+// certo. Backups sem registro (de versões anteriores) são reconhecidos pelo nome.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::archive::PARTIAL_SUFFIX;
+use crate::enums::ArchiveFormat;
+use crate::repository;
+use crate::world_group::MANIFEST_NAME;
+
+pub const CATALOG_NAME: &str = "backups.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRecord {
+    // Nome do arquivo ou da pasta do backup
+    pub name: String,
+    pub format: ArchiveFormat,
+    // Segundos desde a época
+    pub created: u64,
+    // Algum arquivo do mundo não pôde ser lido e ficou de fora
+    #[serde(default)]
+    pub incomplete: bool,
+    // Arquivos do mundo continuaram mudando durante a cópia
+    #[serde(default)]
+    pub inconsistent: bool,
+    // Impressão digital do mundo no momento do backup
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+}
+
+impl BackupRecord {
+    pub fn path(&self, backup_folder: &Path) -> PathBuf {
+        backup_folder.join(&self.name)
+    }
+}
+
+// Formato de um backup sem registro, pelo nome (ou pelo manifesto, numa pasta)
+pub fn infer_format(path: &Path) -> Option<ArchiveFormat> {
+    if path.is_dir() {
+        return path
+            .join(MANIFEST_NAME)
+            .is_file()
+            .then_some(ArchiveFormat::Folder);
+    }
+    let name = path.file_name()?.to_str()?;
+    [
+        ArchiveFormat::Repository,
+        ArchiveFormat::TarZst,
+        ArchiveFormat::McWorld,
+        ArchiveFormat::Zip,
+    ]
+    .into_iter()
+    .find(|format| {
+        format
+            .extension()
+            .is_some_and(|extension| name.ends_with(&format!(".{}", extension)))
+    })
+}
+
+// Nome do backup sem o sufixo do formato
+pub fn strip_extension(name: &str, format: ArchiveFormat) -> &str {
+    format
+        .extension()
+        .and_then(|extension| name.strip_suffix(&format!(".{}", extension)))
+        .unwrap_or(name)
+}
+
+fn read_records(backup_folder: &Path) -> Vec<BackupRecord> {
+    fs::read(backup_folder.join(CATALOG_NAME))
+        .ok()
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default()
+}
+
+// Backups da pasta: os registrados que ainda existem e os sem registro
+pub fn load(backup_folder: &Path) -> Vec<BackupRecord> {
+    let mut records: Vec<BackupRecord> = read_records(backup_folder)
+        .into_iter()
+        .filter(|record| record.path(backup_folder).exists())
+        .collect();
+
+    let Ok(entries) = fs::read_dir(backup_folder) else {
+        return records;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Fora da contagem: o catálogo, arquivos temporários (começam com `.`),
+        // backups incompletos (`.partial`) e os backups já registrados
+        if name == CATALOG_NAME
+            || name.starts_with('.')
+            || name.ends_with(PARTIAL_SUFFIX)
+            || records.iter().any(|record| record.name == name)
+        {
+            continue;
+        }
+        let Some(format) = infer_format(&entry.path()) else {
+            continue;
+        };
+        let created = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs())
+            .unwrap_or_default();
+        records.push(BackupRecord {
+            name,
+            format,
+            created,
+            incomplete: false,
+            inconsistent: false,
+            fingerprint: None,
+        });
+    }
+
+    records.sort_by_key(|record| record.created);
+    records
+}
+
+pub fn save(backup_folder: &Path, records: &[BackupRecord]) -> io::Result<()> {
+    let contents = serde_json::to_vec_pretty(records).map_err(io::Error::other)?;
+    fs::write(backup_folder.join(CATALOG_NAME), contents)
+}
+
+// Registro de um backup pelo caminho, procurando no catálogo da pasta onde ele está
+pub fn find(backup_path: &Path) -> Option<BackupRecord> {
+    let backup_folder = backup_path.parent()?;
+    let name = backup_path.file_name()?.to_string_lossy().to_string();
+    load(backup_folder)
+        .into_iter()
+        .find(|record| record.name == name)
+}
+
+// Apaga um backup. Retorna o erro de leitura de um snapshot ilegível, apagado sem ser
+// lido.
+pub fn remove_backup(backup_folder: &Path, record: &BackupRecord) -> io::Result<Option<io::Error>> {
+    let path = record.path(backup_folder);
+    match record.format {
+        ArchiveFormat::Folder => fs::remove_dir_all(path).map(|()| None),
+        ArchiveFormat::Repository => repository::release(&path),
+        _ => fs::remove_file(path).map(|()| None),
+    }
+}
@@ -0,0 +1,184 @@
+// Leitura da linha de comando da JVM do cliente.
+//
+// No Windows a linha de comando chega como uma única string (Win32_Process.CommandLine)
+// e é separada aqui com as mesmas regras do `CommandLineToArgvW`. No macOS o `ps`
+// perde as aspas, então valores com espaço chegam quebrados em vários argumentos;
+// `path_arg_value` junta esses pedaços de volta.
+
+use std::path::{Path, PathBuf};
+
+// Separa uma linha de comando do Windows em argumentos: aspas agrupam, `\"` é uma
+// aspa literal e barras invertidas só são especiais antes de uma aspa.
+#[cfg(any(windows, test))]
+pub fn split_windows_command_line(command_line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut chars = command_line.trim_start().chars().peekable();
+
+    // O executável não passa pelas regras de barra invertida (`C:\Program Files\`)
+    let mut program = String::new();
+    if chars.peek() == Some(&'"') {
+        chars.next();
+        program.extend(chars.by_ref().take_while(|c| *c != '"'));
+    }
+    while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+        program.push(c);
+    }
+    if program.is_empty() {
+        return args;
+    }
+    args.push(program);
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return args;
+        }
+
+        let mut arg = String::new();
+        let mut in_quotes = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    let mut backslashes = 1;
+                    while chars.next_if_eq(&'\\').is_some() {
+                        backslashes += 1;
+                    }
+                    if chars.peek() == Some(&'"') {
+                        arg.extend(std::iter::repeat_n('\\', backslashes / 2));
+                        if backslashes % 2 == 1 {
+                            chars.next();
+                            arg.push('"');
+                        }
+                    } else {
+                        arg.extend(std::iter::repeat_n('\\', backslashes));
+                    }
+                }
+                '"' if in_quotes && chars.peek() == Some(&'"') => {
+                    // `""` dentro de aspas é uma aspa literal
+                    chars.next();
+                    arg.push('"');
+                }
+                '"' => in_quotes = !in_quotes,
+                c if c.is_whitespace() && !in_quotes => break,
+                c => arg.push(c),
+            }
+        }
+        args.push(arg);
+    }
+}
+
+// Valor de uma flag no formato `--flag valor` ou `--flag=valor`.
+pub fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == flag {
+            args.get(i + 1).map(|value| value.as_str())
+        } else {
+            arg.strip_prefix(flag)?.strip_prefix('=')
+        }
+    })
+}
+
+// Valor de uma flag que é um caminho. Se o caminho tinha espaços e não estava entre
+// aspas, os pedaços seguintes (até a próxima flag) são juntados, preferindo a
+// junção mais longa que existe no disco. Se nenhuma existe, vale só o primeiro
+// pedaço: os outros podem ser argumentos soltos que não fazem parte do caminho.
+pub fn path_arg_value(args: &[String], flag: &str) -> Option<PathBuf> {
+    let first = arg_value(args, flag)?;
+    let Some(position) = args.iter().position(|arg| arg == flag) else {
+        return Some(PathBuf::from(first));
+    };
+
+    let pieces: Vec<&str> = args[position + 1..]
+        .iter()
+        .take_while(|arg| !arg.starts_with('-'))
+        .map(|arg| arg.as_str())
+        .collect();
+    if pieces.len() <= 1 {
+        return Some(PathBuf::from(first));
+    }
+
+    (1..=pieces.len())
+        .rev()
+        .map(|count| pieces[..count].join(" "))
+        .find(|candidate| Path::new(candidate).exists())
+        .or_else(|| Some(first.to_string()))
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn splits_quoted_and_unquoted_paths() {
+        assert_eq!(
+            split_windows_command_line(
+                r#""C:\Program Files\Java\bin\javaw.exe" -Xmx2G --gameDir "C:\Users\Ana Maria\.minecraft" --version 1.21"#
+            ),
+            args(&[
+                r"C:\Program Files\Java\bin\javaw.exe",
+                "-Xmx2G",
+                "--gameDir",
+                r"C:\Users\Ana Maria\.minecraft",
+                "--version",
+                "1.21",
+            ])
+        );
+        assert_eq!(
+            split_windows_command_line(r"C:\Java\bin\java.exe   --gameDir C:\mc\"),
+            args(&[r"C:\Java\bin\java.exe", "--gameDir", r"C:\mc\"])
+        );
+        assert!(split_windows_command_line("   ").is_empty());
+    }
+
+    #[test]
+    fn handles_backslashes_before_quotes() {
+        // `\"` é uma aspa literal; `\\"` é uma barra seguida de aspas que fecham
+        assert_eq!(
+            split_windows_command_line(r#"java.exe "a\"b" "C:\dir\\" c\\\"d"#),
+            args(&["java.exe", r#"a"b"#, r"C:\dir\", r#"c\"d"#])
+        );
+    }
+
+    #[test]
+    fn doubled_quotes_inside_quotes_are_literal() {
+        assert_eq!(
+            split_windows_command_line(r#"java.exe "Mundo ""Novo""" --x"#),
+            args(&["java.exe", r#"Mundo "Novo""#, "--x"])
+        );
+    }
+
+    #[test]
+    fn reads_flag_values() {
+        let values = args(&["java", "--gameDir=/tmp/mc", "--username", "Ana"]);
+        assert_eq!(arg_value(&values, "--gameDir"), Some("/tmp/mc"));
+        assert_eq!(arg_value(&values, "--username"), Some("Ana"));
+        assert_eq!(arg_value(&values, "--version"), None);
+    }
+
+    #[test]
+    fn path_value_joins_only_pieces_that_exist() {
+        let base =
+            std::env::temp_dir().join(format!("auto-mine-backup-cmdline-{}", std::process::id()));
+        let dir = base.join("Application Support").join("minecraft");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut pieces: Vec<String> = dir.to_string_lossy().split(' ').map(String::from).collect();
+        let mut values = args(&["java", "--gameDir"]);
+        values.append(&mut pieces);
+        values.extend(args(&["extra", "--version", "1.21"]));
+        assert_eq!(path_arg_value(&values, "--gameDir"), Some(dir.clone()));
+
+        // Nada existe no disco: só o primeiro pedaço, sem colar os argumentos soltos
+        let values = args(&["java", "--gameDir", "/nao/existe", "solto", "--x"]);
+        assert_eq!(
+            path_arg_value(&values, "--gameDir"),
+            Some(PathBuf::from("/nao/existe"))
+        );
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
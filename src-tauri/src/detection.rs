@@ -0,0 +1,472 @@
+// Detecção do Minecraft em execução.
+//
+// Cada plataforma só lista os processos Java (`/proc` no Linux, `ps` nos outros
+// Unix e um script PowerShell com saída JSON no Windows). O resto é comum: achar o
+// processo de cada cliente, ler a linha de comando, mapear a instância do
+// launcher, detectar o loader e descobrir o mundo aberto pelo latest.log (via
+// `LogWatcher`) e/ou pelo session.lock. O resultado é uma `GameSession` tipada por
+// cliente aberto, nunca uma string a ser decodificada.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cmdline;
+use crate::enums::{DetectionError, DetectionStrategy, LogEvent, ModLoader, WorldStatus};
+use crate::launchers::{self, Instance};
+use crate::loaders;
+use crate::log_watcher::LogWatcher;
+use crate::paths;
+use crate::session_lock::{self, LockEvidence};
+
+pub struct JavaProcess {
+    pub pid: u32,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+}
+
+impl JavaProcess {
+    fn is_java(&self) -> bool {
+        // No macOS os argumentos vêm do `ps` separados por espaço, então o caminho
+        // do executável pode estar quebrado em vários pedaços antes da primeira flag.
+        // O nome é separado nas duas barras porque a linha de comando do Windows é
+        // interpretada da mesma forma em qualquer plataforma.
+        self.args
+            .iter()
+            .take_while(|arg| !arg.starts_with('-'))
+            .filter_map(|arg| arg.rsplit(['/', '\\']).next())
+            .any(|name| name.starts_with("java"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Confidence {
+    pub score: f32,
+    pub sources: Vec<&'static str>,
+}
+
+// Id da sessão reportada pela detecção só pelo log, que não conhece o processo
+pub const LOG_SESSION_PID: u32 = 0;
+
+// Um cliente do Minecraft em execução. Vários clientes podem estar abertos ao mesmo
+// tempo; um jogo fechado simplesmente não aparece na lista.
+#[derive(Debug, Clone)]
+pub struct GameSession {
+    pub pid: u32,
+    pub game_dir: Option<PathBuf>,
+    // Erro quando o processo foi encontrado mas o estado dele não pôde ser determinado
+    pub status: Result<WorldStatus, DetectionError>,
+    // Instância de launcher do processo, se houver
+    pub instance: Option<Instance>,
+    // Só preenchida pelas estratégias que usam o session.lock
+    pub confidence: Option<Confidence>,
+}
+
+#[cfg(target_os = "linux")]
+pub fn list_java_processes() -> Result<Vec<JavaProcess>, DetectionError> {
+    let entries = fs::read_dir("/proc")
+        .map_err(|e| DetectionError::ProbeFailed(format!("falha ao ler /proc: {}", e)))?;
+
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            // Apenas as entradas /proc/<pid>
+            let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+            let raw = fs::read(entry.path().join("cmdline")).ok()?;
+            let args = raw
+                .split(|byte| *byte == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect();
+            let cwd = fs::read_link(entry.path().join("cwd")).ok();
+            Some(JavaProcess { pid, args, cwd })
+        })
+        .filter(|process| process.is_java())
+        .collect())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn list_java_processes() -> Result<Vec<JavaProcess>, DetectionError> {
+    let output = std::process::Command::new("ps")
+        .args(["-axww", "-o", "pid=,args="])
+        .output()
+        .map_err(|e| DetectionError::ProbeFailed(format!("falha ao executar ps: {}", e)))?;
+    if !output.status.success() {
+        return Err(DetectionError::ProbeFailed(format!(
+            "erro ao executar ps: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, command_line) = line.trim_start().split_once(' ')?;
+            // Ignora linhas que não começam com um pid
+            let pid = pid.parse::<u32>().ok()?;
+            let args = command_line.split_whitespace().map(String::from).collect();
+            Some(JavaProcess {
+                pid,
+                args,
+                cwd: None,
+            })
+        })
+        .filter(|process| process.is_java())
+        .collect())
+}
+
+// Saída do script PowerShell: `ConvertTo-Json` dos processos java/javaw. O
+// CommandLine vem nulo para processos de outros usuários.
+#[cfg(any(windows, test))]
+#[derive(serde::Deserialize)]
+struct WindowsProcess {
+    #[serde(rename = "ProcessId")]
+    pid: u32,
+    #[serde(rename = "CommandLine")]
+    command_line: Option<String>,
+}
+
+#[cfg(any(windows, test))]
+pub fn parse_process_list(json: &str) -> Result<Vec<JavaProcess>, DetectionError> {
+    let processes: Vec<WindowsProcess> = serde_json::from_str(json).map_err(|e| {
+        DetectionError::ProbeFailed(format!("saída inválida do PowerShell: {} ({})", json, e))
+    })?;
+    Ok(processes
+        .into_iter()
+        .filter_map(|process| {
+            Some(JavaProcess {
+                pid: process.pid,
+                args: cmdline::split_windows_command_line(&process.command_line?),
+                cwd: None,
+            })
+        })
+        .filter(|process| process.is_java())
+        .collect())
+}
+
+pub fn probe(
+    processes: Vec<JavaProcess>,
+    instances: &[Instance],
+    main_classes: &[String],
+    log_watcher: &mut LogWatcher,
+    strategy: DetectionStrategy,
+) -> Vec<GameSession> {
+    processes
+        .into_iter()
+        .filter(|process| process.args.iter().any(|arg| main_classes.contains(arg)))
+        .map(|process| probe_process(process, instances, log_watcher, strategy))
+        .collect()
+}
+
+fn probe_process(
+    process: JavaProcess,
+    instances: &[Instance],
+    log_watcher: &mut LogWatcher,
+    strategy: DetectionStrategy,
+) -> GameSession {
+    let game_dir = cmdline::path_arg_value(&process.args, "--gameDir");
+    let instance = launchers::find_instance_for_process(
+        instances,
+        &process.args,
+        game_dir.as_deref(),
+        process.cwd.as_deref(),
+    )
+    .cloned();
+    let loader = loaders::detect_loader(
+        &process.args.join(" "),
+        instance.as_ref().and_then(|instance| instance.loader),
+    );
+
+    // Launchers como o Prism não passam --gameDir; usa a pasta da instância
+    let Some(game_dir) =
+        game_dir.or_else(|| instance.as_ref().map(|instance| instance.game_dir.clone()))
+    else {
+        let status = match cmdline::arg_value(&process.args, "--quickPlayPath")
+            .filter(|path| path.ends_with(".json"))
+        {
+            Some(quick_play_path) => probe_quick_play(quick_play_path, loader),
+            None => Err(DetectionError::GameDirUnknown(process.args.join(" "))),
+        };
+        return GameSession {
+            pid: process.pid,
+            game_dir: None,
+            status,
+            instance,
+            confidence: None,
+        };
+    };
+
+    let (status, confidence) = match probe_game_dir(&game_dir, log_watcher, strategy, loader) {
+        Ok((status, confidence)) => (Ok(status), confidence),
+        Err(e) => (Err(e), None),
+    };
+    GameSession {
+        pid: process.pid,
+        game_dir: Some(game_dir),
+        status,
+        instance,
+        confidence,
+    }
+}
+
+// Detecção só pelo latest.log, sem olhar os processos: útil quando o processo do
+// jogo não é visível (sandbox, outro usuário). Usa o diretório do jogo com o
+// latest.log modificado mais recentemente; o jogo é considerado fechado quando o
+// último evento do log é o "Stopping!" do cliente. Se o jogo travar sem escrever
+// essa linha, o último mundo continua sendo reportado até um novo log começar.
+pub fn probe_logs(
+    game_dirs: &[PathBuf],
+    instances: &[Instance],
+    log_watcher: &mut LogWatcher,
+) -> Result<Vec<GameSession>, DetectionError> {
+    let Some(game_dir) = game_dirs
+        .iter()
+        .filter_map(|game_dir| {
+            let modified = fs::metadata(game_dir.join("logs").join("latest.log"))
+                .and_then(|metadata| metadata.modified())
+                .ok()?;
+            Some((game_dir, modified))
+        })
+        .max_by_key(|(_, modified)| *modified)
+        .map(|(game_dir, _)| game_dir)
+    else {
+        return Err(DetectionError::ProbeFailed(format!(
+            "nenhum latest.log encontrado em: {:?}",
+            game_dirs
+        )));
+    };
+
+    let log_path = game_dir.join("logs").join("latest.log");
+    log_watcher.poll(&log_path).map_err(|e| {
+        DetectionError::ProbeFailed(format!("falha ao ler {}: {}", log_path.display(), e))
+    })?;
+
+    let instance =
+        launchers::find_instance_for_process(instances, &[], Some(game_dir), None).cloned();
+    let loader = instance
+        .as_ref()
+        .and_then(|instance| instance.loader)
+        .unwrap_or(ModLoader::Unknown);
+
+    let status = match log_watcher.last_event(&log_path) {
+        Some(LogEvent::IntegratedServerStarted(level_name)) => WorldStatus::InWorld(
+            world_folder(&game_dir.join("saves"), level_name),
+            Some(game_dir.clone()),
+            loader,
+        ),
+        Some(LogEvent::ConnectingToServer(address)) => WorldStatus::Multiplayer(address.clone()),
+        Some(LogEvent::IntegratedServerStopped) => WorldStatus::MainMenu,
+        Some(LogEvent::ClientStopping) | None => return Ok(Vec::new()),
+    };
+
+    Ok(vec![GameSession {
+        pid: LOG_SESSION_PID,
+        game_dir: Some(game_dir.clone()),
+        status: Ok(status),
+        instance,
+        confidence: None,
+    }])
+}
+
+// O log traz o nome de exibição; o backup precisa da pasta do mundo
+fn world_folder(saves_dir: &Path, level_name: &str) -> String {
+    paths::resolve_world_folder(saves_dir, level_name)
+        .and_then(|folder| {
+            folder
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| level_name.to_string())
+}
+
+// Pesos de cada evidência na estratégia combinada
+const LOG_CONFIDENCE: f32 = 0.7;
+const LOCK_HELD_CONFIDENCE: f32 = 0.95;
+const LOCK_MTIME_CONFIDENCE: f32 = 0.5;
+const MIN_CONFIDENCE: f32 = 0.5;
+
+struct Candidate {
+    // None significa "nenhum mundo aberto" (menu)
+    world: Option<String>,
+    confidence: f32,
+    source: &'static str,
+}
+
+// Descobre o mundo aberto no diretório do jogo usando a estratégia configurada:
+// o latest.log (lido de forma incremental), o session.lock dos mundos, ou os dois
+// combinados somando a confiança de cada evidência.
+fn probe_game_dir(
+    game_dir: &Path,
+    log_watcher: &mut LogWatcher,
+    strategy: DetectionStrategy,
+    loader: ModLoader,
+) -> Result<(WorldStatus, Option<Confidence>), DetectionError> {
+    let log_path = game_dir.join("logs").join("latest.log");
+    let saves_dir = game_dir.join("saves");
+    let mut candidates = Vec::new();
+
+    let log_found =
+        strategy != DetectionStrategy::SessionLock && log_watcher.poll(&log_path).is_ok();
+    if log_found {
+        match log_watcher.last_event(&log_path) {
+            Some(LogEvent::IntegratedServerStarted(level_name)) => {
+                candidates.push(Candidate {
+                    world: Some(world_folder(&saves_dir, level_name)),
+                    confidence: LOG_CONFIDENCE,
+                    source: "latest.log",
+                });
+            }
+            Some(LogEvent::IntegratedServerStopped) => candidates.push(Candidate {
+                world: None,
+                confidence: LOG_CONFIDENCE,
+                source: "latest.log",
+            }),
+            // Em multiplayer não há mundo local com session.lock para confirmar
+            Some(LogEvent::ConnectingToServer(address)) => {
+                return Ok((WorldStatus::Multiplayer(address.clone()), None));
+            }
+            // O processo ainda existe enquanto o jogo fecha
+            Some(LogEvent::ClientStopping) | None => {}
+        }
+    }
+
+    if strategy == DetectionStrategy::Log {
+        return match candidates.pop() {
+            Some(Candidate {
+                world: Some(world), ..
+            }) => Ok((
+                WorldStatus::InWorld(world, Some(game_dir.to_path_buf()), loader),
+                None,
+            )),
+            _ if !log_found => Err(DetectionError::LogNotFound(game_dir.to_path_buf())),
+            // Log sem nenhuma entrada de mundo ou o mundo foi fechado: menu
+            _ => Ok((WorldStatus::MainMenu, None)),
+        };
+    }
+
+    // O latest.log é criado quando o jogo inicia
+    let session_started = fs::metadata(&log_path)
+        .and_then(|metadata| metadata.created())
+        .ok();
+    match session_lock::inspect_saves(&saves_dir, session_started) {
+        LockEvidence::Held(folder) => candidates.push(Candidate {
+            world: Some(folder),
+            confidence: LOCK_HELD_CONFIDENCE,
+            source: "session.lock",
+        }),
+        LockEvidence::NoneHeld => candidates.push(Candidate {
+            world: None,
+            confidence: LOCK_HELD_CONFIDENCE,
+            source: "session.lock",
+        }),
+        LockEvidence::RecentlyOpened(folder) => candidates.push(Candidate {
+            world: Some(folder),
+            confidence: LOCK_MTIME_CONFIDENCE,
+            source: "session.lock (mtime)",
+        }),
+        LockEvidence::Unknown => {}
+    }
+
+    // Soma a confiança das evidências que apontam para o mesmo resultado
+    let mut totals: Vec<(Option<String>, f32, Vec<&str>)> = Vec::new();
+    for candidate in candidates {
+        match totals
+            .iter_mut()
+            .find(|(world, _, _)| *world == candidate.world)
+        {
+            Some((_, confidence, sources)) => {
+                *confidence = (*confidence + candidate.confidence).min(1.0);
+                sources.push(candidate.source);
+            }
+            None => totals.push((
+                candidate.world,
+                candidate.confidence,
+                vec![candidate.source],
+            )),
+        }
+    }
+
+    match totals
+        .into_iter()
+        .max_by(|(_, a, _), (_, b, _)| a.total_cmp(b))
+    {
+        Some((Some(world), score, sources)) if score >= MIN_CONFIDENCE => Ok((
+            WorldStatus::InWorld(world, Some(game_dir.to_path_buf()), loader),
+            Some(Confidence { score, sources }),
+        )),
+        Some((None, score, sources)) => {
+            Ok((WorldStatus::MainMenu, Some(Confidence { score, sources })))
+        }
+        _ if !log_found && strategy == DetectionStrategy::Combined => {
+            Err(DetectionError::LogNotFound(game_dir.to_path_buf()))
+        }
+        _ => Ok((WorldStatus::MainMenu, None)),
+    }
+}
+
+fn probe_quick_play(
+    quick_play_path: &str,
+    loader: ModLoader,
+) -> Result<WorldStatus, DetectionError> {
+    let quick_play: Option<serde_json::Value> = fs::read_to_string(quick_play_path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok());
+    let first_entry = quick_play.as_ref().and_then(|value| match value {
+        serde_json::Value::Array(entries) => entries.first(),
+        other => Some(other),
+    });
+
+    let world_id = first_entry
+        .and_then(|entry| entry.get("id"))
+        .and_then(|id| id.as_str())
+        .filter(|id| !id.is_empty());
+    let is_multiplayer = first_entry
+        .and_then(|entry| entry.get("type"))
+        .and_then(|kind| kind.as_str())
+        == Some("multiplayer");
+
+    match world_id {
+        Some(world_id) if is_multiplayer => Ok(WorldStatus::Multiplayer(world_id.to_string())),
+        Some(world_id) => Ok(WorldStatus::InWorld(world_id.to_string(), None, loader)),
+        None => Err(DetectionError::QuickPlayUnreadable(
+            quick_play_path.to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Saída do script do `PowerShellProbe` com um cliente, um java de outro usuário
+    // (CommandLine nulo) e um java que não é o Minecraft
+    const POWERSHELL_OUTPUT: &str = r#"[{"ProcessId":7312,"CommandLine":"\"C:\\Program Files\\Java\\jdk-21\\bin\\javaw.exe\" -Xmx4G -Djava.library.path=C:\\Users\\Ana\\AppData\\Roaming\\.minecraft\\natives -cp C:\\libs\\a.jar net.minecraft.client.main.Main --username Ana --gameDir \"C:\\Users\\Ana\\AppData\\Roaming\\.minecraft\" --version 1.21.1"},{"ProcessId":1044,"CommandLine":null},{"ProcessId":980,"CommandLine":"C:\\Windows\\notepad.exe"}]"#;
+
+    #[test]
+    fn parses_powershell_process_list() {
+        let processes = parse_process_list(POWERSHELL_OUTPUT).unwrap();
+        assert_eq!(processes.len(), 1);
+
+        let process = &processes[0];
+        assert_eq!(process.pid, 7312);
+        assert_eq!(
+            process.args[0],
+            r"C:\Program Files\Java\jdk-21\bin\javaw.exe"
+        );
+        assert!(process
+            .args
+            .contains(&"net.minecraft.client.main.Main".to_string()));
+        assert_eq!(
+            cmdline::arg_value(&process.args, "--gameDir"),
+            Some(r"C:\Users\Ana\AppData\Roaming\.minecraft")
+        );
+    }
+
+    #[test]
+    fn rejects_powershell_output_without_process_id() {
+        let output = r#"[{"CommandLine":"javaw.exe net.minecraft.client.main.Main"}]"#;
+        assert!(matches!(
+            parse_process_list(output),
+            Err(DetectionError::ProbeFailed(_))
+        ));
+    }
+}
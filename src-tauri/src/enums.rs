@@ -1,7 +1,220 @@
+// Estado de um cliente em execução; um jogo fechado não tem `GameSession`.
 #[derive(Debug, Clone, PartialEq)]
 pub enum WorldStatus {
-    Stopped,
     MainMenu,
     Multiplayer(String),
-    InWorld(String),
+    // Nome do mundo, o --gameDir da instância em execução (quando conhecido) e o
+    // loader de mods do cliente.
+    InWorld(String, Option<std::path::PathBuf>, ModLoader),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SavesOrigin {
+    GameDir,
+    Override,
+    PlatformDefault(&'static str),
+}
+
+impl std::fmt::Display for SavesOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SavesOrigin::GameDir => write!(f, "--gameDir do jogo em execução"),
+            SavesOrigin::Override => write!(f, "configurado em minecraft_dir"),
+            SavesOrigin::PlatformDefault(label) => write!(f, "padrão {}", label),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LauncherKind {
+    Prism,
+    MultiMC,
+    CurseForge,
+    ATLauncher,
+}
+
+impl std::fmt::Display for LauncherKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LauncherKind::Prism => write!(f, "Prism Launcher"),
+            LauncherKind::MultiMC => write!(f, "MultiMC"),
+            LauncherKind::CurseForge => write!(f, "CurseForge"),
+            LauncherKind::ATLauncher => write!(f, "ATLauncher"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ModLoader {
+    Vanilla,
+    Fabric,
+    Forge,
+    NeoForge,
+    Quilt,
+    Unknown,
+}
+
+impl std::fmt::Display for ModLoader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModLoader::Vanilla => write!(f, "Vanilla"),
+            ModLoader::Fabric => write!(f, "Fabric"),
+            ModLoader::Forge => write!(f, "Forge"),
+            ModLoader::NeoForge => write!(f, "NeoForge"),
+            ModLoader::Quilt => write!(f, "Quilt"),
+            ModLoader::Unknown => write!(f, "Desconhecido"),
+        }
+    }
+}
+
+// Formato de um backup. O `McWorld` é um zip com o mundo na raiz, que o Bedrock
+// importa direto.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Zip,
+    McWorld,
+    TarZst,
+    // Cópia simples das pastas, sem compressão
+    Folder,
+    // Snapshot num repositório com deduplicação
+    Repository,
+}
+
+impl ArchiveFormat {
+    pub fn from_name(name: &str) -> ArchiveFormat {
+        match name {
+            "tar.zst" | "tar_zst" => ArchiveFormat::TarZst,
+            "folder" => ArchiveFormat::Folder,
+            "repository" => ArchiveFormat::Repository,
+            _ => ArchiveFormat::Zip,
+        }
+    }
+
+    // Sufixo do nome do backup; backups em pasta não têm
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            ArchiveFormat::Zip => Some("zip"),
+            ArchiveFormat::McWorld => Some("mcworld"),
+            ArchiveFormat::TarZst => Some("tar.zst"),
+            ArchiveFormat::Folder => None,
+            ArchiveFormat::Repository => Some("snapshot.json"),
+        }
+    }
+}
+
+impl std::fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveFormat::Zip => write!(f, "zip"),
+            ArchiveFormat::McWorld => write!(f, "mcworld"),
+            ArchiveFormat::TarZst => write!(f, "tar.zst"),
+            ArchiveFormat::Folder => write!(f, "pasta"),
+            ArchiveFormat::Repository => write!(f, "repositório"),
+        }
+    }
+}
+
+// Método de compressão das entradas do zip
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompressionMethod {
+    // Sem compressão
+    Stored,
+    Deflate,
+    Zstd,
+    Bzip2,
+}
+
+impl CompressionMethod {
+    pub fn from_name(name: &str) -> CompressionMethod {
+        match name {
+            "stored" => CompressionMethod::Stored,
+            "zstd" => CompressionMethod::Zstd,
+            "bzip2" => CompressionMethod::Bzip2,
+            _ => CompressionMethod::Deflate,
+        }
+    }
+}
+
+impl std::fmt::Display for CompressionMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompressionMethod::Stored => write!(f, "sem compressão"),
+            CompressionMethod::Deflate => write!(f, "Deflate"),
+            CompressionMethod::Zstd => write!(f, "Zstd"),
+            CompressionMethod::Bzip2 => write!(f, "Bzip2"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogEvent {
+    // "Starting integrated server for world '<nome>'"
+    IntegratedServerStarted(String),
+    // "Stopping server": o jogador saiu do mundo local
+    IntegratedServerStopped,
+    // "Connecting to <endereço>, <porta>"
+    ConnectingToServer(String),
+    // "Stopping!" do cliente ao fechar o jogo
+    ClientStopping,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetectionStrategy {
+    // Última entrada de mundo do latest.log
+    Log,
+    // Lock do session.lock dos mundos
+    SessionLock,
+    // Log e session.lock juntos, com pontuação de confiança
+    Combined,
+}
+
+impl DetectionStrategy {
+    pub fn from_name(name: &str) -> DetectionStrategy {
+        match name {
+            "session_lock" => DetectionStrategy::SessionLock,
+            "combined" => DetectionStrategy::Combined,
+            _ => DetectionStrategy::Log,
+        }
+    }
+}
+
+// Falhas da detecção. São diferentes de "jogo fechado": quando a detecção falha não
+// dá para saber se o jogo está aberto, então nenhum backup de saída é disparado.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DetectionError {
+    // Não foi possível listar os processos (PowerShell, /proc, ps) ou a saída da
+    // listagem não é a esperada
+    ProbeFailed(String),
+    // Jogo em execução sem --gameDir e sem instância de launcher correspondente
+    GameDirUnknown(String),
+    // O quickPlayPath não pôde ser lido ou não tem o id do mundo
+    QuickPlayUnreadable(String),
+    // Jogo em execução mas o latest.log não existe
+    LogNotFound(std::path::PathBuf),
+}
+
+impl std::fmt::Display for DetectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DetectionError::ProbeFailed(reason) => {
+                write!(f, "falha ao executar a detecção: {}", reason)
+            }
+            DetectionError::GameDirUnknown(command_line) => write!(
+                f,
+                "não foi possível determinar o gameDir. Linha de comando: {}",
+                command_line
+            ),
+            DetectionError::QuickPlayUnreadable(path) => write!(
+                f,
+                "não foi possível determinar o ID do mundo a partir do quickPlayPath: {}",
+                path
+            ),
+            DetectionError::LogNotFound(game_dir) => write!(
+                f,
+                "não foi possível encontrar o log em: {}",
+                game_dir.display()
+            ),
+        }
+    }
 }
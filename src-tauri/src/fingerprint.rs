@@ -0,0 +1,235 @@
+// Impressão digital de um mundo, para não repetir backups de um mundo que não mudou.
+//
+// Junta o caminho, o tamanho e a data de modificação de cada arquivo das pastas do
+// grupo e o `LastPlayed` do level.dat num SHA-256. Com `content`, o conteúdo de cada
+// arquivo também entra: é mais lento, mas não depende das datas de modificação.
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::archive::FILES_TO_IGNORE;
+use crate::catalog::BackupRecord;
+use crate::integrity::{self, HashingReader, BUFFER_SIZE};
+use crate::nbt;
+use crate::world_group::WorldGroup;
+
+// Calcula a impressão digital numa thread de bloqueio, como a compactação
+pub async fn compute(group: &WorldGroup, content: bool) -> io::Result<String> {
+    let group = group.clone();
+    tauri::async_runtime::spawn_blocking(move || fingerprint(&group, content))
+        .await
+        .map_err(|e| io::Error::other(format!("tarefa de impressão digital falhou: {}", e)))?
+}
+
+// Se `latest`, o último backup, já tem o mundo com essa impressão digital. Um backup
+// incompleto ou sem impressão digital (de versões anteriores) não serve de referência.
+pub fn is_unchanged_since(latest: &BackupRecord, fingerprint: &str) -> bool {
+    !latest.incomplete && latest.fingerprint.as_deref() == Some(fingerprint)
+}
+
+fn fingerprint(group: &WorldGroup, content: bool) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    for dir in group.dirs() {
+        // Ordem fixa, para que a mesma pasta sempre dê o mesmo resultado
+        for entry in WalkDir::new(&dir).sort_by_file_name() {
+            let entry = entry?;
+            let path = entry.path();
+            let ignored = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| FILES_TO_IGNORE.contains(&name));
+            if !path.is_file() || ignored {
+                continue;
+            }
+
+            let metadata = entry.metadata()?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |modified| modified.as_nanos());
+            let relative = path.strip_prefix(&group.root).unwrap_or(path);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update(metadata.len().to_le_bytes());
+            hasher.update(modified.to_le_bytes());
+            if content {
+                let file = BufReader::with_capacity(BUFFER_SIZE, File::open(path)?);
+                let mut reader = HashingReader::new(file);
+                io::copy(&mut reader, &mut io::sink())?;
+                hasher.update(reader.finish("").sha256.as_bytes());
+            }
+        }
+        hasher.update(last_played(&dir).to_le_bytes());
+    }
+
+    Ok(integrity::to_hex(&hasher.finalize()))
+}
+
+// `LastPlayed` do level.dat do Java, em ms (0 quando não há, como no Bedrock)
+fn last_played(world_dir: &Path) -> i64 {
+    nbt::read_gzip_file(&world_dir.join("level.dat"))
+        .ok()
+        .as_ref()
+        .and_then(|root| root.get("Data"))
+        .and_then(|data| data.get("LastPlayed"))
+        .and_then(|last_played| last_played.as_i64())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use crate::enums::ArchiveFormat;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "auto-mine-backup-fingerprint-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // level.dat em gzip com a raiz `{ Data: { LastPlayed: <last_played> } }`
+    fn write_level_dat(path: &Path, last_played: i64) {
+        let mut data = vec![10, 0, 0, 10, 0, 4];
+        data.extend_from_slice(b"Data");
+        data.extend_from_slice(&[4, 0, 10]);
+        data.extend_from_slice(b"LastPlayed");
+        data.extend_from_slice(&last_played.to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    fn modified(path: &Path) -> SystemTime {
+        fs::metadata(path).unwrap().modified().unwrap()
+    }
+
+    fn world(dir: &Path) -> WorldGroup {
+        let world = dir.join("saves/world");
+        fs::create_dir_all(world.join("region")).unwrap();
+        write_level_dat(&world.join("level.dat"), 1_700_000_000_000);
+        fs::write(world.join("region/r.0.0.mca"), [1; 4096]).unwrap();
+        WorldGroup::single("world", &world)
+    }
+
+    fn record(fingerprint: Option<String>) -> BackupRecord {
+        BackupRecord {
+            name: "backup_world_20260101_120000.zip".to_string(),
+            format: ArchiveFormat::Zip,
+            created: 0,
+            incomplete: false,
+            inconsistent: false,
+            fingerprint,
+        }
+    }
+
+    #[test]
+    fn untouched_world_is_skipped() {
+        let dir = temp_dir("untouched");
+        let group = world(&dir);
+        let first = fingerprint(&group, false).unwrap();
+        assert_eq!(fingerprint(&group, false).unwrap(), first);
+        assert!(is_unchanged_since(&record(Some(first.clone())), &first));
+
+        // Um backup incompleto não serve de referência
+        let mut incomplete = record(Some(first.clone()));
+        incomplete.incomplete = true;
+        assert!(!is_unchanged_since(&incomplete, &first));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn region_size_and_mtime_change_the_fingerprint() {
+        let dir = temp_dir("region");
+        let group = world(&dir);
+        let region = group.root.join("world/region/r.0.0.mca");
+        let before = fingerprint(&group, false).unwrap();
+
+        let original = modified(&region);
+        set_modified(&region, original + Duration::from_secs(5));
+        let touched = fingerprint(&group, false).unwrap();
+        assert_ne!(touched, before);
+
+        // Mesmo conteúdo inicial, tamanho diferente e a data de antes
+        File::options()
+            .write(true)
+            .open(&region)
+            .unwrap()
+            .set_len(8192)
+            .unwrap();
+        set_modified(&region, original);
+        let resized = fingerprint(&group, false).unwrap();
+        assert_ne!(resized, before);
+        assert_ne!(resized, touched);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn last_played_changes_the_fingerprint() {
+        let dir = temp_dir("last-played");
+        let group = world(&dir);
+        let level_dat = group.root.join("world/level.dat");
+        let before = fingerprint(&group, false).unwrap();
+
+        // Mesmo tamanho e mesma data de modificação, só o LastPlayed muda
+        let original = modified(&level_dat);
+        let size = fs::metadata(&level_dat).unwrap().len();
+        write_level_dat(&level_dat, 1_700_000_060_000);
+        set_modified(&level_dat, original);
+        assert_eq!(fs::metadata(&level_dat).unwrap().len(), size);
+        assert_eq!(last_played(&group.dirs()[0]), 1_700_000_060_000);
+        assert_ne!(fingerprint(&group, false).unwrap(), before);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn records_without_fingerprint_never_skip() {
+        // Registro gravado por uma versão sem impressão digital
+        let old: BackupRecord = serde_json::from_str(
+            r#"{"name":"backup_world_20250101_120000.zip","format":"zip","created":1}"#,
+        )
+        .unwrap();
+        assert_eq!(old.fingerprint, None);
+        assert!(!is_unchanged_since(&old, ""));
+        assert!(!is_unchanged_since(&record(None), ""));
+
+        let dir = temp_dir("old-record");
+        let group = world(&dir);
+        assert!(!is_unchanged_since(
+            &old,
+            &fingerprint(&group, false).unwrap()
+        ));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
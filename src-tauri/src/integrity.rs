@@ -0,0 +1,283 @@
+// Conferência dos backups.
+//
+// Enquanto o backup é escrito, cada arquivo é resumido com SHA-256 e o tamanho, e a
+// lista vai para `auto-mine-backup-hashes.json` dentro do próprio backup. Com o
+// backup pronto, ele é aberto de novo e cada entrada é lida até o fim (no zip, isso
+// confere também o CRC) e comparada com a lista.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::enums::ArchiveFormat;
+use crate::repository;
+use crate::world_group::MANIFEST_NAME;
+
+pub const HASHES_NAME: &str = "auto-mine-backup-hashes.json";
+
+// Tamanho dos blocos lidos dos arquivos do mundo e do buffer de escrita dos backups
+pub const BUFFER_SIZE: usize = 256 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileHash {
+    // Caminho dentro do backup, separado por `/`
+    pub name: String,
+    pub size: u64,
+    // SHA-256 em hexadecimal
+    pub sha256: String,
+}
+
+// Resumo em hexadecimal minúsculo, como fica nas listas de hashes
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Calcula o SHA-256 do que passa por ele
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> HashingReader<R> {
+        HashingReader {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    pub fn finish(self, name: &str) -> FileHash {
+        FileHash {
+            name: name.to_string(),
+            size: self.size,
+            sha256: to_hex(&self.hasher.finalize()),
+        }
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        self.size += read as u64;
+        Ok(read)
+    }
+}
+
+fn hash_reader<R: Read>(name: &str, reader: R) -> io::Result<FileHash> {
+    let mut reader = HashingReader::new(reader);
+    io::copy(&mut reader, &mut io::sink())?;
+    Ok(reader.finish(name))
+}
+
+// Lê todas as entradas do backup em `path` e confere com `expected`
+pub fn verify(format: ArchiveFormat, path: &Path, expected: &[FileHash]) -> io::Result<()> {
+    let mut remaining: HashMap<&str, &FileHash> = expected
+        .iter()
+        .map(|hash| (hash.name.as_str(), hash))
+        .collect();
+    let mut check = |found: FileHash| {
+        if found.name == MANIFEST_NAME || found.name == HASHES_NAME {
+            return Ok(());
+        }
+        match remaining.remove(found.name.as_str()) {
+            Some(hash) if *hash == found => Ok(()),
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} não confere com o arquivo original", found.name),
+            )),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} não deveria estar no backup", found.name),
+            )),
+        }
+    };
+
+    match format {
+        ArchiveFormat::Zip | ArchiveFormat::McWorld => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))?;
+            for index in 0..archive.len() {
+                let file = archive.by_index(index)?;
+                if !file.is_dir() {
+                    let name = file.name().to_string();
+                    check(hash_reader(&name, file)?)?;
+                }
+            }
+        }
+        ArchiveFormat::TarZst => {
+            let mut decoder = zstd::Decoder::new(File::open(path)?)?;
+            decoder.window_log_max(31)?;
+            let mut archive = tar::Archive::new(decoder);
+            for entry in archive.entries()? {
+                let entry = entry?;
+                if entry.header().entry_type().is_file() {
+                    let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+                    check(hash_reader(&name, entry)?)?;
+                }
+            }
+        }
+        ArchiveFormat::Repository => {
+            repository::for_each_file(path, |file, reader| {
+                check(hash_reader(&file.name, reader)?)
+            })?;
+        }
+        ArchiveFormat::Folder => {
+            for entry in WalkDir::new(path) {
+                let entry = entry?;
+                if entry.file_type().is_file() {
+                    let name = entry
+                        .path()
+                        .strip_prefix(path)
+                        .unwrap()
+                        .components()
+                        .map(|component| component.as_os_str().to_string_lossy().to_string())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    let file = BufReader::with_capacity(BUFFER_SIZE, File::open(entry.path())?);
+                    check(hash_reader(&name, file)?)?;
+                }
+            }
+        }
+    }
+
+    match remaining.keys().next() {
+        Some(name) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} está faltando no backup", name),
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    const FILES: [(&str, &[u8]); 3] = [
+        ("level.dat", b"level"),
+        ("region/r.0.0.mca", b"region 0 0"),
+        ("region/r.0.-1.mca", b"region 0 -1"),
+    ];
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "auto-mine-backup-integrity-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn expected() -> Vec<FileHash> {
+        FILES
+            .iter()
+            .map(|(name, data)| hash_reader(name, *data).unwrap())
+            .collect()
+    }
+
+    // Grava `files` num backup em `dst`, com o manifesto e a lista de hashes, que a
+    // conferência ignora
+    fn write(format: ArchiveFormat, dst: &Path, files: &[(&str, &[u8])]) {
+        let mut files = files.to_vec();
+        files.push((MANIFEST_NAME, b"{}"));
+        files.push((HASHES_NAME, b"[]"));
+        match format {
+            ArchiveFormat::Zip => {
+                let mut zip = zip::ZipWriter::new(File::create(dst).unwrap());
+                for (name, data) in files {
+                    zip.start_file(name, zip::write::SimpleFileOptions::default())
+                        .unwrap();
+                    zip.write_all(data).unwrap();
+                }
+                zip.finish().unwrap();
+            }
+            ArchiveFormat::TarZst => {
+                let encoder = zstd::Encoder::new(File::create(dst).unwrap(), 3).unwrap();
+                let mut builder = tar::Builder::new(encoder);
+                for (name, data) in files {
+                    let mut header = tar::Header::new_gnu();
+                    header.set_size(data.len() as u64);
+                    header.set_mode(0o644);
+                    builder.append_data(&mut header, name, data).unwrap();
+                }
+                builder.into_inner().unwrap().finish().unwrap();
+            }
+            ArchiveFormat::Folder => {
+                for (name, data) in files {
+                    let path = dst.join(name);
+                    fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    fs::write(path, data).unwrap();
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn verify_error(format: ArchiveFormat, dst: &Path, files: &[(&str, &[u8])]) -> String {
+        write(format, dst, files);
+        let error = verify(format, dst, &expected()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        error.to_string()
+    }
+
+    fn check_format(format: ArchiveFormat, extension: &str) {
+        let dir = temp_dir(extension);
+        let backup = |name: &str| dir.join(format!("{}{}", name, extension));
+
+        write(format, &backup("ok"), &FILES);
+        verify(format, &backup("ok"), &expected()).unwrap();
+
+        // Um byte trocado numa entrada
+        let mut flipped = FILES;
+        flipped[1].1 = b"region 0 1";
+        let error = verify_error(format, &backup("flipped"), &flipped);
+        assert!(error.contains("region/r.0.0.mca não confere"), "{}", error);
+
+        // Uma entrada faltando
+        let error = verify_error(format, &backup("missing"), &FILES[..2]);
+        assert!(
+            error.contains("region/r.0.-1.mca está faltando"),
+            "{}",
+            error
+        );
+
+        // Uma entrada que não está na lista de hashes
+        let mut extra = FILES.to_vec();
+        extra.push(("region/r.1.0.mca", b"region 1 0"));
+        let error = verify_error(format, &backup("extra"), &extra);
+        assert!(
+            error.contains("region/r.1.0.mca não deveria estar"),
+            "{}",
+            error
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn verifies_zip_backups() {
+        check_format(ArchiveFormat::Zip, ".zip");
+    }
+
+    #[test]
+    fn verifies_tar_zst_backups() {
+        check_format(ArchiveFormat::TarZst, ".tar.zst");
+    }
+
+    #[test]
+    fn verifies_folder_backups() {
+        check_format(ArchiveFormat::Folder, "");
+    }
+}
@@ -0,0 +1,244 @@
+// Descoberta de instâncias de launchers de terceiros (Prism, MultiMC, CurseForge,
+// ATLauncher) e mapeamento de um processo do jogo para a instância correspondente.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::enums::{LauncherKind, ModLoader};
+use crate::loaders;
+
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub launcher: LauncherKind,
+    pub name: String,
+    // Pasta da instância dentro do launcher
+    pub root: PathBuf,
+    // Diretório do jogo (equivalente ao `.minecraft`)
+    pub game_dir: PathBuf,
+    pub loader: Option<ModLoader>,
+}
+
+impl Instance {
+    pub fn saves_dir(&self) -> PathBuf {
+        self.game_dir.join("saves")
+    }
+}
+
+fn default_launcher_dirs() -> Vec<(LauncherKind, PathBuf)> {
+    let mut roots = Vec::new();
+
+    if let Some(data_dir) = dirs::data_dir() {
+        roots.push((LauncherKind::Prism, data_dir.join("PrismLauncher")));
+        roots.push((LauncherKind::MultiMC, data_dir.join("multimc")));
+        roots.push((LauncherKind::MultiMC, data_dir.join("MultiMC")));
+        roots.push((LauncherKind::ATLauncher, data_dir.join("ATLauncher")));
+    }
+
+    if let Some(home) = dirs::home_dir() {
+        let curseforge = if cfg!(target_os = "macos") {
+            home.join("Documents").join("curseforge")
+        } else {
+            home.join("curseforge")
+        };
+        roots.push((
+            LauncherKind::CurseForge,
+            curseforge.join("minecraft").join("Instances"),
+        ));
+
+        if cfg!(target_os = "linux") {
+            let flatpak = home.join(".var").join("app");
+            roots.push((
+                LauncherKind::Prism,
+                flatpak
+                    .join("org.prismlauncher.PrismLauncher")
+                    .join("data")
+                    .join("PrismLauncher"),
+            ));
+            roots.push((
+                LauncherKind::ATLauncher,
+                flatpak.join("com.atlauncher.ATLauncher").join("data"),
+            ));
+        }
+    }
+
+    roots
+}
+
+// Lê um arquivo `chave=valor` no formato usado pelo Prism/MultiMC (`instance.cfg`,
+// `prismlauncher.cfg`, `multimc.cfg`).
+fn read_cfg_value(path: &Path, key: &str) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+    contents.lines().find_map(|line| {
+        let (line_key, value) = line.split_once('=')?;
+        (line_key.trim() == key).then(|| value.trim().to_string())
+    })
+}
+
+fn read_json_name(path: &Path, pointers: &[&str]) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    pointers.iter().find_map(|pointer| {
+        json.pointer(pointer)
+            .and_then(|value| value.as_str())
+            .map(String::from)
+    })
+}
+
+// Pastas que podem conter instâncias dentro do diretório de um launcher. O Prism e o
+// MultiMC permitem mudar a pasta de instâncias através da chave `InstanceDir`.
+fn instances_dirs_of(launcher_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    for cfg_name in ["prismlauncher.cfg", "multimc.cfg"] {
+        if let Some(instance_dir) = read_cfg_value(&launcher_dir.join(cfg_name), "InstanceDir") {
+            dirs.push(launcher_dir.join(instance_dir));
+        }
+    }
+    dirs.push(launcher_dir.join("instances"));
+    dirs.push(launcher_dir.join("Instances"));
+    dirs.push(launcher_dir.to_path_buf());
+
+    dirs
+}
+
+fn launcher_of(launcher_dir: &Path, hint: Option<LauncherKind>) -> Option<LauncherKind> {
+    if launcher_dir.join("prismlauncher.cfg").exists() {
+        return Some(LauncherKind::Prism);
+    }
+    if launcher_dir.join("multimc.cfg").exists() {
+        return Some(LauncherKind::MultiMC);
+    }
+    hint
+}
+
+fn read_instance(instance_dir: &Path, launcher: Option<LauncherKind>) -> Option<Instance> {
+    let folder_name = instance_dir.file_name()?.to_string_lossy().to_string();
+
+    let instance_cfg = instance_dir.join("instance.cfg");
+    if instance_cfg.exists() {
+        // Versões novas do Prism usam `minecraft`, as antigas e o MultiMC `.minecraft`
+        let game_dir = ["minecraft", ".minecraft"]
+            .iter()
+            .map(|name| instance_dir.join(name))
+            .find(|dir| dir.is_dir())
+            .unwrap_or_else(|| instance_dir.join(".minecraft"));
+        return Some(Instance {
+            launcher: launcher.unwrap_or(LauncherKind::MultiMC),
+            name: read_cfg_value(&instance_cfg, "name").unwrap_or(folder_name),
+            root: instance_dir.to_path_buf(),
+            game_dir,
+            loader: loaders::instance_loader(instance_dir),
+        });
+    }
+
+    let curseforge_json = instance_dir.join("minecraftinstance.json");
+    if curseforge_json.exists() {
+        return Some(Instance {
+            launcher: LauncherKind::CurseForge,
+            name: read_json_name(&curseforge_json, &["/name"]).unwrap_or(folder_name),
+            root: instance_dir.to_path_buf(),
+            game_dir: instance_dir.to_path_buf(),
+            loader: loaders::instance_loader(instance_dir),
+        });
+    }
+
+    let atlauncher_json = instance_dir.join("instance.json");
+    if atlauncher_json.exists() {
+        return Some(Instance {
+            launcher: LauncherKind::ATLauncher,
+            name: read_json_name(&atlauncher_json, &["/launcher/name", "/name"])
+                .unwrap_or(folder_name),
+            root: instance_dir.to_path_buf(),
+            game_dir: instance_dir.to_path_buf(),
+            loader: loaders::instance_loader(instance_dir),
+        });
+    }
+
+    None
+}
+
+// Lista as instâncias dos locais padrão de cada launcher e dos diretórios extras
+// configurados pelo usuário (útil para instalações portáteis do MultiMC/ATLauncher).
+pub fn discover_instances(extra_launcher_dirs: &[PathBuf]) -> Vec<Instance> {
+    let launcher_dirs = default_launcher_dirs()
+        .into_iter()
+        .map(|(launcher, dir)| (Some(launcher), dir));
+    let extra_dirs = extra_launcher_dirs.iter().map(|dir| (None, dir.clone()));
+
+    let mut instances: Vec<Instance> = Vec::new();
+    for (hint, launcher_dir) in launcher_dirs.chain(extra_dirs) {
+        if !launcher_dir.is_dir() {
+            continue;
+        }
+        let launcher = launcher_of(&launcher_dir, hint);
+
+        for instances_dir in instances_dirs_of(&launcher_dir) {
+            let Ok(entries) = fs::read_dir(&instances_dir) else {
+                continue;
+            };
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                if !path.is_dir() || instances.iter().any(|instance| instance.root == path) {
+                    continue;
+                }
+                if let Some(instance) = read_instance(&path, launcher) {
+                    instances.push(instance);
+                }
+            }
+        }
+    }
+
+    instances
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Procura a instância de um processo do jogo, nesta ordem: pelo --gameDir, pelo
+// diretório de trabalho do processo (o Prism e o MultiMC iniciam o jogo dentro da
+// pasta da instância) e, por fim, pelo caminho da instância aparecendo em algum
+// argumento da linha de comando (ex.: `-Djava.library.path=<instância>/natives`).
+pub fn find_instance_for_process<'a>(
+    instances: &'a [Instance],
+    args: &[String],
+    game_dir: Option<&Path>,
+    cwd: Option<&Path>,
+) -> Option<&'a Instance> {
+    if let Some(game_dir) = game_dir {
+        if let Some(instance) = instances
+            .iter()
+            .find(|instance| same_path(&instance.game_dir, game_dir))
+        {
+            return Some(instance);
+        }
+    }
+
+    if let Some(cwd) = cwd {
+        if let Some(instance) = instances
+            .iter()
+            .find(|instance| same_path(&instance.game_dir, cwd) || same_path(&instance.root, cwd))
+        {
+            return Some(instance);
+        }
+    }
+
+    instances
+        .iter()
+        .filter(|instance| {
+            let root = instance.root.to_string_lossy();
+            args.iter().any(|arg| {
+                arg.match_indices(root.as_ref()).any(|(index, _)| {
+                    // Evita que `instances/Foo` case com `instances/Foo Bar`
+                    arg[index + root.len()..]
+                        .chars()
+                        .next()
+                        .is_none_or(|next| next == '/' || next == '\\' || next == '"')
+                })
+            })
+        })
+        .max_by_key(|instance| instance.root.as_os_str().len())
+}
@@ -0,0 +1,125 @@
+// Classes principais conhecidas de clientes do Minecraft (vanilla e com mods) e
+// heurísticas para descobrir qual loader de mods está em uso.
+
+use std::fs;
+use std::path::Path;
+
+use crate::enums::ModLoader;
+
+// Classes principais reconhecidas por padrão. O usuário pode acrescentar outras
+// em `client_main_classes` no settings.json.
+pub const DEFAULT_CLIENT_MAIN_CLASSES: [&str; 10] = [
+    "net.minecraft.client.main.Main",
+    "net.fabricmc.loader.impl.launch.knot.KnotClient",
+    "net.fabricmc.loader.launch.knot.KnotClient",
+    "org.quiltmc.loader.impl.launch.knot.KnotClient",
+    "cpw.mods.bootstraplauncher.BootstrapLauncher",
+    "cpw.mods.modlauncher.Launcher",
+    "net.minecraft.launchwrapper.Launch",
+    "net.neoforged.fml.startup.Client",
+    // O Prism e o MultiMC iniciam o jogo através dos próprios EntryPoints, que
+    // carregam a classe principal do Minecraft dentro do mesmo processo.
+    "org.prismlauncher.EntryPoint",
+    "org.multimc.EntryPoint",
+];
+
+pub fn client_main_classes(extra: &[String]) -> Vec<String> {
+    let mut classes: Vec<String> = DEFAULT_CLIENT_MAIN_CLASSES
+        .iter()
+        .map(|class| class.to_string())
+        .collect();
+    for class in extra {
+        if !classes.contains(class) {
+            classes.push(class.clone());
+        }
+    }
+    classes
+}
+
+// A ordem importa: `neoforgeclient` contém `forgeclient` e instâncias do Quilt
+// costumam carregar bibliotecas do Fabric.
+const COMMAND_LINE_HINTS: [(&str, ModLoader); 13] = [
+    ("--fml.neoForgeVersion", ModLoader::NeoForge),
+    ("neoforgeclient", ModLoader::NeoForge),
+    ("net.neoforged", ModLoader::NeoForge),
+    ("--fml.forgeVersion", ModLoader::Forge),
+    ("forgeclient", ModLoader::Forge),
+    ("fmlclient", ModLoader::Forge),
+    ("net.minecraftforge", ModLoader::Forge),
+    ("cpw.mods.", ModLoader::Forge),
+    ("org.quiltmc.loader", ModLoader::Quilt),
+    ("quilt-loader", ModLoader::Quilt),
+    ("net.fabricmc.loader", ModLoader::Fabric),
+    ("fabric-loader", ModLoader::Fabric),
+    ("net.minecraft.client.main.Main", ModLoader::Vanilla),
+];
+
+// Procura pistas do loader na linha de comando (classe principal, `--version`
+// como `fabric-loader-0.15.0-1.20.4`, `--launchTarget`, classpath). Quando a
+// linha de comando não diz nada (Prism/MultiMC), usa o loader da instância.
+pub fn detect_loader(command_line: &str, instance_loader: Option<ModLoader>) -> ModLoader {
+    let lowercase = command_line.to_lowercase();
+    COMMAND_LINE_HINTS
+        .iter()
+        .find(|(hint, _)| lowercase.contains(&hint.to_lowercase()))
+        .map(|(_, loader)| *loader)
+        .or(instance_loader)
+        .unwrap_or(ModLoader::Unknown)
+}
+
+fn loader_from_name(name: &str) -> Option<ModLoader> {
+    let name = name.to_lowercase();
+    if name.contains("neoforge") || name.contains("neoforged") {
+        Some(ModLoader::NeoForge)
+    } else if name.contains("forge") {
+        Some(ModLoader::Forge)
+    } else if name.contains("quilt") {
+        Some(ModLoader::Quilt)
+    } else if name.contains("fabric") {
+        Some(ModLoader::Fabric)
+    } else {
+        None
+    }
+}
+
+// Lê o loader configurado numa instância de launcher: `mmc-pack.json` (Prism e
+// MultiMC), `minecraftinstance.json` (CurseForge) ou `instance.json` (ATLauncher).
+pub fn instance_loader(instance_dir: &Path) -> Option<ModLoader> {
+    let read_json = |name: &str| -> Option<serde_json::Value> {
+        let contents = fs::read_to_string(instance_dir.join(name)).ok()?;
+        serde_json::from_str(&contents).ok()
+    };
+
+    if let Some(pack) = read_json("mmc-pack.json") {
+        // Instâncias do Quilt também trazem `net.fabricmc.intermediary`, então os
+        // componentes são avaliados juntos, na ordem de prioridade de `loader_from_name`
+        let components = pack.get("components")?.as_array()?;
+        let uids: Vec<&str> = components
+            .iter()
+            .filter_map(|component| component.get("uid")?.as_str())
+            .collect();
+        return Some(loader_from_name(&uids.join(" ")).unwrap_or(ModLoader::Vanilla));
+    }
+
+    if let Some(instance) = read_json("minecraftinstance.json") {
+        return Some(
+            instance
+                .pointer("/baseModLoader/name")
+                .and_then(|name| name.as_str())
+                .and_then(loader_from_name)
+                .unwrap_or(ModLoader::Vanilla),
+        );
+    }
+
+    if let Some(instance) = read_json("instance.json") {
+        return Some(
+            instance
+                .pointer("/launcher/loaderVersion/type")
+                .and_then(|name| name.as_str())
+                .and_then(loader_from_name)
+                .unwrap_or(ModLoader::Vanilla),
+        );
+    }
+
+    None
+}
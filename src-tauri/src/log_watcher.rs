@@ -0,0 +1,395 @@
+// Leitura incremental do latest.log do cliente.
+//
+// O `LogWatcher` guarda, para cada arquivo de log, até onde já leu. A cada chamada de
+// `poll` só os bytes novos são lidos e transformados em `LogEvent`s, e os salvamentos
+// do mundo são contados. Se o arquivo encolher (truncado) ou for substituído (o jogo
+// compacta o latest.log antigo e cria um novo ao iniciar), a leitura recomeça do
+// início do arquivo novo.
+
+use std::collections::HashMap;
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::enums::LogEvent;
+
+const CONNECTING_PATTERN: &str = "Connecting to ";
+const INTEGRATED_SERVER_PATTERN: &str = "Starting integrated server for world";
+// Escrito pelo servidor integrado ao sair do mundo ("[Server thread/INFO]: Stopping server")
+const INTEGRATED_SERVER_STOPPING_PATTERN: &str = "]: Stopping server";
+// Escrito pelo cliente ao fechar ("[Render thread/INFO]: Stopping!")
+const CLIENT_STOPPING_PATTERN: &str = "]: Stopping!";
+// Linhas de um salvamento do mundo. O autosave e a pausa escrevem "Saving chunks for
+// level '<nível>'/<dimensão>" antes de gravar cada dimensão; os salvamentos completos
+// (sair do mundo, save-all flush) terminam com "ThreadedAnvilChunkStorage (<mundo>):
+// All chunks are saved" até a 1.17 e "ThreadedAnvilChunkStorage: All dimensions are
+// saved" a partir da 1.18.
+const SAVE_STARTED_PATTERN: &str = "Saving chunks for level";
+const SAVE_COMPLETE_PATTERNS: [&str; 2] = ["All chunks are saved", "All dimensions are saved"];
+
+// Transforma uma linha do log em um evento, se ela for relevante.
+pub fn parse_line(line: &str) -> Option<LogEvent> {
+    if let Some((_, world_name)) = line.rsplit_once(INTEGRATED_SERVER_PATTERN) {
+        let world_name = world_name.trim_start().trim_start_matches('\'');
+        let world_name = world_name
+            .trim_end()
+            .strip_suffix('\'')
+            .unwrap_or(world_name);
+        return Some(LogEvent::IntegratedServerStarted(
+            world_name.trim().to_string(),
+        ));
+    }
+
+    if line
+        .trim_end()
+        .ends_with(INTEGRATED_SERVER_STOPPING_PATTERN)
+    {
+        return Some(LogEvent::IntegratedServerStopped);
+    }
+
+    if line.trim_end().ends_with(CLIENT_STOPPING_PATTERN) {
+        return Some(LogEvent::ClientStopping);
+    }
+
+    if let Some((_, address)) = line.split(',').next()?.rsplit_once(CONNECTING_PATTERN) {
+        return Some(LogEvent::ConnectingToServer(address.trim().to_string()));
+    }
+
+    None
+}
+
+// Se a linha diz que o salvamento do mundo terminou.
+fn is_save_complete(line: &str) -> bool {
+    SAVE_COMPLETE_PATTERNS
+        .iter()
+        .any(|pattern| line.contains(pattern))
+}
+
+// Onde o log está numa sequência de linhas de salvamento
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SaveBurst {
+    Idle,
+    // Começou e ainda não terminou
+    Started,
+    // Terminou e já foi contado, mas as linhas do mesmo salvamento podem continuar
+    // (uma por dimensão)
+    Counted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FileIdentity {
+    #[cfg(unix)]
+    inode: u64,
+    created: Option<SystemTime>,
+}
+
+impl FileIdentity {
+    fn of(metadata: &Metadata) -> FileIdentity {
+        #[cfg(unix)]
+        use std::os::unix::fs::MetadataExt;
+
+        FileIdentity {
+            #[cfg(unix)]
+            inode: metadata.ino(),
+            created: metadata.created().ok(),
+        }
+    }
+}
+
+struct LogCursor {
+    identity: FileIdentity,
+    offset: u64,
+    // Fim de linha ainda não escrito pelo jogo
+    partial_line: Vec<u8>,
+    last_event: Option<LogEvent>,
+    // Salvamentos do mundo terminados no arquivo atual. Linhas de salvamento seguidas
+    // (uma por dimensão) contam como um salvamento só, quando chega a linha de fim ou,
+    // no autosave, que não tem uma, a primeira linha de outra coisa.
+    saves: u64,
+    save_burst: SaveBurst,
+}
+
+impl LogCursor {
+    fn new(identity: FileIdentity) -> LogCursor {
+        LogCursor {
+            identity,
+            offset: 0,
+            partial_line: Vec::new(),
+            last_event: None,
+            saves: 0,
+            save_burst: SaveBurst::Idle,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LogWatcher {
+    cursors: HashMap<PathBuf, LogCursor>,
+}
+
+impl LogWatcher {
+    pub fn new() -> LogWatcher {
+        LogWatcher::default()
+    }
+
+    // Lê o que foi escrito no log desde a última chamada e retorna os eventos novos.
+    pub fn poll(&mut self, path: &Path) -> io::Result<Vec<LogEvent>> {
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let identity = FileIdentity::of(&metadata);
+
+        let cursor = self
+            .cursors
+            .entry(path.to_path_buf())
+            .or_insert_with(|| LogCursor::new(identity));
+
+        if cursor.identity != identity || metadata.len() < cursor.offset {
+            // Arquivo substituído ou truncado: recomeça do zero
+            *cursor = LogCursor::new(identity);
+        }
+
+        file.seek(SeekFrom::Start(cursor.offset))?;
+        let mut buffer = std::mem::take(&mut cursor.partial_line);
+        let read = file.read_to_end(&mut buffer)?;
+        cursor.offset += read as u64;
+
+        let complete_len = buffer
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |index| index + 1);
+        cursor.partial_line = buffer.split_off(complete_len);
+
+        let text = String::from_utf8_lossy(&buffer);
+        let events: Vec<LogEvent> = text.lines().filter_map(parse_line).collect();
+        for line in text.lines() {
+            cursor.save_burst = if is_save_complete(line) {
+                if cursor.save_burst != SaveBurst::Counted {
+                    cursor.saves += 1;
+                }
+                SaveBurst::Counted
+            } else if line.contains(SAVE_STARTED_PATTERN) {
+                match cursor.save_burst {
+                    SaveBurst::Idle => SaveBurst::Started,
+                    burst => burst,
+                }
+            } else {
+                if cursor.save_burst == SaveBurst::Started {
+                    cursor.saves += 1;
+                }
+                SaveBurst::Idle
+            };
+        }
+
+        if let Some(event) = events.last() {
+            cursor.last_event = Some(event.clone());
+        }
+
+        Ok(events)
+    }
+
+    // Último evento visto no log desde que o arquivo atual foi criado.
+    pub fn last_event(&self, path: &Path) -> Option<&LogEvent> {
+        self.cursors.get(path)?.last_event.as_ref()
+    }
+
+    // Quantos salvamentos do mundo terminaram no log atual até o último `poll`.
+    pub fn saves(&self, path: &Path) -> u64 {
+        self.cursors.get(path).map_or(0, |cursor| cursor.saves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    // Pasta temporária própria de cada teste
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "auto-mine-backup-log-watcher-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn append(path: &Path, text: &str) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    // Logs gravados de clientes reais
+    const SINGLEPLAYER_LOG: &str = include_str!("../tests/fixtures/latest_1.21_singleplayer.log");
+    const MULTIPLAYER_LOG: &str = include_str!("../tests/fixtures/latest_1.16_multiplayer.log");
+
+    fn singleplayer_events() -> Vec<LogEvent> {
+        vec![
+            LogEvent::IntegratedServerStarted("Meu Mundo".to_string()),
+            LogEvent::IntegratedServerStopped,
+            LogEvent::ConnectingToServer("mc.hypixel.net".to_string()),
+            LogEvent::ClientStopping,
+        ]
+    }
+
+    #[test]
+    fn parses_recorded_logs() {
+        let events: Vec<LogEvent> = SINGLEPLAYER_LOG.lines().filter_map(parse_line).collect();
+        assert_eq!(events, singleplayer_events());
+
+        let events: Vec<LogEvent> = MULTIPLAYER_LOG.lines().filter_map(parse_line).collect();
+        assert_eq!(
+            events,
+            vec![
+                LogEvent::ConnectingToServer("play.example.org".to_string()),
+                LogEvent::ClientStopping,
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_lines_that_only_look_like_events() {
+        for line in [
+            "[14:03:02] [Server thread/INFO]: Starting integrated minecraft server version 1.21.1",
+            "[14:20:10] [Server thread/INFO]: Stopping singleplayer server as player logged out",
+            "[14:21:02] [Render thread/INFO]: [CHAT] <Ana> Stopping server amanhã",
+            "[14:21:02] [Render thread/INFO]: [CHAT] Welcome to Hypixel!",
+        ] {
+            assert_eq!(parse_line(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn poll_keeps_partial_last_line_for_next_read() {
+        let dir = temp_dir("partial");
+        let log = dir.join("latest.log");
+        let world_line = SINGLEPLAYER_LOG
+            .find("Starting integrated server for world")
+            .unwrap();
+        // O jogo ainda está no meio da linha do mundo
+        let (written, rest) = SINGLEPLAYER_LOG.split_at(world_line + 20);
+        append(&log, written);
+
+        let mut watcher = LogWatcher::new();
+        assert_eq!(watcher.poll(&log).unwrap(), vec![]);
+        assert_eq!(watcher.last_event(&log), None);
+
+        append(&log, rest);
+        assert_eq!(watcher.poll(&log).unwrap(), singleplayer_events());
+        assert_eq!(watcher.last_event(&log), Some(&LogEvent::ClientStopping));
+        // Nada de novo
+        assert_eq!(watcher.poll(&log).unwrap(), vec![]);
+        assert_eq!(watcher.saves(&log), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn poll_restarts_after_truncation() {
+        let dir = temp_dir("truncation");
+        let log = dir.join("latest.log");
+        append(&log, SINGLEPLAYER_LOG);
+        let mut watcher = LogWatcher::new();
+        assert_eq!(watcher.poll(&log).unwrap().len(), 4);
+
+        // Mesmo arquivo, reescrito com menos conteúdo
+        fs::write(&log, MULTIPLAYER_LOG).unwrap();
+        assert_eq!(
+            watcher.poll(&log).unwrap(),
+            vec![
+                LogEvent::ConnectingToServer("play.example.org".to_string()),
+                LogEvent::ClientStopping,
+            ]
+        );
+        assert_eq!(watcher.saves(&log), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[cfg_attr(
+        windows,
+        ignore = "o NTFS mantém a data de criação de um arquivo recriado com o mesmo nome"
+    )]
+    fn poll_restarts_after_rotation() {
+        let dir = temp_dir("rotation");
+        let log = dir.join("latest.log");
+        append(&log, MULTIPLAYER_LOG);
+        let mut watcher = LogWatcher::new();
+        assert_eq!(watcher.poll(&log).unwrap().len(), 2);
+
+        // O jogo guarda o log antigo e cria um latest.log novo, maior que o anterior
+        fs::rename(&log, dir.join("2026-10-18-1.log")).unwrap();
+        append(&log, SINGLEPLAYER_LOG);
+        assert_eq!(watcher.poll(&log).unwrap(), singleplayer_events());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn counts_each_save_burst_once() {
+        let dir = temp_dir("saves");
+        let log = dir.join("latest.log");
+        append(
+            &log,
+            "[12:00:00] [Server thread/INFO]: Starting integrated server for world 'Mundo'\n",
+        );
+        let mut watcher = LogWatcher::new();
+        watcher.poll(&log).unwrap();
+        assert_eq!(watcher.saves(&log), 0);
+
+        // Pausa: uma linha por dimensão, e o salvamento ainda não terminou
+        append(
+            &log,
+            "[12:05:00] [Server thread/INFO]: Saving chunks for level 'ServerLevel[Mundo]'/minecraft:overworld\n\
+             [12:05:00] [Server thread/INFO]: Saving chunks for level 'ServerLevel[Mundo]'/minecraft:the_nether\n",
+        );
+        watcher.poll(&log).unwrap();
+        assert_eq!(watcher.saves(&log), 0);
+
+        // O fim do mesmo salvamento chega noutro poll e conta uma vez só
+        append(
+            &log,
+            "[12:05:00] [Server thread/INFO]: Saving chunks for level 'ServerLevel[Mundo]'/minecraft:the_end\n\
+             [12:05:01] [Server thread/INFO]: ThreadedAnvilChunkStorage (Mundo): All chunks are saved\n\
+             [12:05:01] [Server thread/INFO]: ThreadedAnvilChunkStorage: All dimensions are saved\n\
+             [12:05:30] [Render thread/INFO]: [CHAT] Olá\n",
+        );
+        watcher.poll(&log).unwrap();
+        assert_eq!(watcher.saves(&log), 1);
+
+        // Autosave, sem linha de fim: termina na próxima linha de outra coisa
+        append(
+            &log,
+            "[12:10:00] [Server thread/INFO]: Saving chunks for level 'ServerLevel[Mundo]'/minecraft:overworld\n",
+        );
+        watcher.poll(&log).unwrap();
+        assert_eq!(watcher.saves(&log), 1);
+        append(
+            &log,
+            "[12:10:05] [Render thread/INFO]: [CHAT] Olá de novo\n",
+        );
+        watcher.poll(&log).unwrap();
+        assert_eq!(watcher.saves(&log), 2);
+
+        // Até a 1.17, um "All chunks are saved" depois de cada dimensão
+        append(
+            &log,
+            "[12:15:00] [Server thread/INFO]: Saving chunks for level 'ServerLevel[Mundo]'/minecraft:overworld\n\
+             [12:15:00] [Server thread/INFO]: ThreadedAnvilChunkStorage (Mundo): All chunks are saved\n\
+             [12:15:00] [Server thread/INFO]: Saving chunks for level 'ServerLevel[Mundo]'/minecraft:the_nether\n\
+             [12:15:00] [Server thread/INFO]: ThreadedAnvilChunkStorage (DIM-1): All chunks are saved\n",
+        );
+        watcher.poll(&log).unwrap();
+        assert_eq!(watcher.saves(&log), 3);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
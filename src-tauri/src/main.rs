@@ -1,8 +1,32 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod archive;
+mod bedrock;
+mod catalog;
+mod cmdline;
+mod detection;
 mod enums;
+mod fingerprint;
+mod integrity;
+mod launchers;
+mod loaders;
+mod log_watcher;
+mod nbt;
+mod paths;
+mod probes;
+mod rcon;
+mod repository;
+mod restore;
+mod server;
+mod session;
+mod session_lock;
+mod snapshot;
 mod utils;
+mod world_group;
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 
 use tauri::{
     menu::{Menu, MenuItem},
@@ -12,6 +36,8 @@ use tauri::{
 use tauri_plugin_store::StoreExt;
 use tokio::time::{self, Duration};
 
+use crate::log_watcher::LogWatcher;
+use crate::session::{GameActivity, SessionTracker, SessionTransition};
 use crate::utils::{check_minecraft_running, log_to_file_and_emit};
 
 #[tokio::main]
@@ -23,6 +49,10 @@ async fn main() {
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
         .setup(|app| {
+            app.manage(Arc::new(Mutex::new(LogWatcher::new())));
+            app.manage(probes::from_settings(app.handle()));
+            app.manage(GameActivity::default());
+
             let quit_i = MenuItem::with_id(app, "quit", "Sair", true, None::<&str>)?;
             let configure_i = MenuItem::with_id(
                 app,
@@ -32,7 +62,18 @@ async fn main() {
                 None::<&str>,
             )?;
 
-            let menu = Menu::with_items(app, &[&configure_i, &quit_i])?;
+            let restore_i =
+                MenuItem::with_id(app, "restore", "Restaurar backup", true, None::<&str>)?;
+
+            let export_i = MenuItem::with_id(
+                app,
+                "export",
+                "Exportar snapshot do repositório",
+                true,
+                None::<&str>,
+            )?;
+
+            let menu = Menu::with_items(app, &[&configure_i, &restore_i, &export_i, &quit_i])?;
 
             TrayIconBuilder::new()
                 .icon(app.default_window_icon().unwrap().clone())
@@ -46,6 +87,18 @@ async fn main() {
                             window.set_focus().unwrap();
                         }
                     }
+                    "restore" => {
+                        let app_handle = app.app_handle().clone();
+                        tauri::async_runtime::spawn_blocking(move || {
+                            utils::restore_backup_with_dialog(&app_handle)
+                        });
+                    }
+                    "export" => {
+                        let app_handle = app.app_handle().clone();
+                        tauri::async_runtime::spawn_blocking(move || {
+                            utils::export_snapshot_with_dialog(&app_handle)
+                        });
+                    }
                     _ => log_to_file_and_emit(
                         &app.app_handle().clone(),
                         format!("menu item {:?} not handled", event.id),
@@ -59,55 +112,86 @@ async fn main() {
             let store = app.store("settings.json")?;
             let store_15_secs = store.clone();
 
+            utils::remove_partial_backups(&app_handle, &store);
+
             let time_to_backup = store.get("time_to_backup").unwrap_or_default();
 
             tauri::async_runtime::spawn(async move {
+                let mut session = SessionTracker::restore(&store_15_secs);
+                let mut running_servers = HashSet::new();
                 let mut interval = time::interval(Duration::from_secs(15));
                 loop {
                     interval.tick().await;
-                    let status = check_minecraft_running(&app_handle_15_secs).await;
-                    match status {
-                        enums::WorldStatus::InWorld(world_name) => {
+                    utils::check_dedicated_servers(
+                        &app_handle_15_secs,
+                        &store_15_secs,
+                        &mut running_servers,
+                    )
+                    .await;
+
+                    let sessions = match check_minecraft_running(&app_handle_15_secs).await {
+                        Ok(sessions) => sessions,
+                        Err(e) => {
+                            // Sem saber se o jogo está aberto, não dispara o backup de saída
                             log_to_file_and_emit(
                                 &app_handle_15_secs,
-                                format!(
-                                    "[FAST CHECK] Minecraft World: {}. Setando como último mundo para backup.",
-                                    world_name
-                                ),
+                                format!("[FAST CHECK] Detecção do Minecraft falhou: {}", e),
                             );
-                            store_15_secs
-                                .set("last_backed_up_world", world_name);
-                                
+                            continue;
                         }
-                        enums::WorldStatus::Stopped => {
-                            let last_world = store_15_secs
-                                .get("last_backed_up_world")
-                                .unwrap_or_default();
-
-                            if last_world.is_null() {
-                                log_to_file_and_emit(
-                                    &app_handle_15_secs,
-                                    "[FAST CHECK] Sem backup pendente."
-                                        .to_string(),
-                                );
-                            } else {
-                                log_to_file_and_emit(
-                                    &app_handle_15_secs,
-                                    format!(
-                                        "[FAST CHECK] Minecraft World: {}. Iniciando backup...",
-                                        last_world
-                                    ),
-                                );
-                                utils::backup_minecraft_world(
-                                    &app_handle_15_secs,
-                                    &store_15_secs,
-                                    last_world.as_str().unwrap().to_string(),
-                                )
-                                .await;
-                                store_15_secs.delete("last_backed_up_world");
-                            }
+                    };
+
+                    app_handle_15_secs
+                        .state::<GameActivity>()
+                        .set_running(!sessions.is_empty() || !running_servers.is_empty());
+
+                    let transitions = session.update(&sessions);
+                    if transitions.is_empty() && !session.has_pending() {
+                        if sessions.is_empty() {
+                            log_to_file_and_emit(
+                                &app_handle_15_secs,
+                                "[FAST CHECK] Sem backup pendente.",
+                            );
                         }
-                        _ => {}
+                        continue;
+                    }
+
+                    for transition in transitions {
+                        let message = match transition {
+                            SessionTransition::WorldJoined(joined) => format!(
+                                "[FAST CHECK] [pid {}] Entrou no mundo: {} ({}).",
+                                joined.pid, joined.world_name, joined.loader
+                            ),
+                            SessionTransition::WorldSwitched(left, joined) => format!(
+                                "[FAST CHECK] [pid {}] Trocou do mundo {} para o mundo {} ({}).",
+                                joined.pid, left.world_name, joined.world_name, joined.loader
+                            ),
+                            SessionTransition::WorldLeft(left) => format!(
+                                "[FAST CHECK] [pid {}] Saiu do mundo: {}.",
+                                left.pid, left.world_name
+                            ),
+                        };
+                        log_to_file_and_emit(&app_handle_15_secs, message);
+                    }
+                    session.persist(&store_15_secs);
+
+                    while let Some(left) = session.next_pending() {
+                        log_to_file_and_emit(
+                            &app_handle_15_secs,
+                            format!(
+                                "[FAST CHECK] [pid {}] Minecraft World: {}. Iniciando backup de saída...",
+                                left.pid, left.world_name
+                            ),
+                        );
+                        utils::backup_minecraft_world(
+                            &app_handle_15_secs,
+                            &store_15_secs,
+                            left.world_name,
+                            left.game_dir,
+                        )
+                        .await;
+                        session.finish_pending();
+                        session.persist(&store_15_secs);
                     }
                 }
             });
@@ -125,36 +209,83 @@ async fn main() {
 
                 loop {
                     interval.tick().await;
-                    let status = check_minecraft_running(&app_handle).await;
-                    log_to_file_and_emit(
-                        &app_handle,
-                        format!("[BACKGROUND TASK] Minecraft status: {:?}", status),
-                    );
+                    for server in utils::dedicated_servers(&app_handle) {
+                        if !server.is_running() {
+                            continue;
+                        }
+                        log_to_file_and_emit(
+                            &app_handle,
+                            format!(
+                                "[BACKGROUND TASK] Servidor dedicado: {:?} (mundo: {}). Iniciando backup...",
+                                server.dir, server.level_name
+                            ),
+                        );
+                        utils::backup_server_world(&app_handle, &store, &server, true).await;
+                    }
+                    utils::backup_bedrock_worlds(&app_handle, &store).await;
 
-                    match status {
-                        enums::WorldStatus::InWorld(world_name) => {
+                    let sessions = match check_minecraft_running(&app_handle).await {
+                        Ok(sessions) => sessions,
+                        Err(e) => {
                             log_to_file_and_emit(
                                 &app_handle,
-                                format!(
-                                    "[BACKGROUND TASK] Minecraft World: {}. Iniciando backup...",
-                                    world_name
-                                ),
+                                format!("[BACKGROUND TASK] Detecção do Minecraft falhou: {}", e),
                             );
-                            utils::backup_minecraft_world(&app_handle, &store, world_name).await;
+                            continue;
                         }
-                        _ => {}
+                    };
+                    if sessions.is_empty() {
+                        log_to_file_and_emit(
+                            &app_handle,
+                            "[BACKGROUND TASK] Minecraft status: Stopped",
+                        );
+                    }
+
+                    for session in &sessions {
+                        log_to_file_and_emit(
+                            &app_handle,
+                            format!(
+                                "[BACKGROUND TASK] [pid {}] Minecraft status: {:?} (gameDir: {:?})",
+                                session.pid, session.status, session.game_dir
+                            ),
+                        );
+                    }
+
+                    // A espera pelo autosave é uma só para todos os clientes abertos
+                    let in_world: Vec<_> = sessions
+                        .into_iter()
+                        .filter_map(|session| match session.status {
+                            Ok(enums::WorldStatus::InWorld(world_name, game_dir, loader)) => {
+                                Some((session.pid, world_name, game_dir, loader))
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    let game_dirs: Vec<_> = in_world
+                        .iter()
+                        .filter_map(|(_, _, game_dir, _)| game_dir.clone())
+                        .collect();
+                    utils::wait_for_autosave(&app_handle, &store, &game_dirs).await;
+
+                    for (pid, world_name, game_dir, loader) in in_world {
+                        log_to_file_and_emit(
+                            &app_handle,
+                            format!(
+                                "[BACKGROUND TASK] [pid {}] Minecraft World: {} ({}). Iniciando backup...",
+                                pid, world_name, loader
+                            ),
+                        );
+                        utils::backup_minecraft_world(&app_handle, &store, world_name, game_dir)
+                            .await;
                     }
                 }
             });
 
             Ok(())
         })
-        .on_window_event(|app_handle, event| match event {
-            tauri::WindowEvent::CloseRequested { api, .. } => {
-                api.prevent_close();
-                app_handle.hide().unwrap();
-            }
-            _ => {}
+        .on_window_event(|app_handle, event| if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+            api.prevent_close();
+            app_handle.hide().unwrap();
         })
         .run(context)
         .expect("failed to run app");
@@ -0,0 +1,269 @@
+// Leitor mínimo de NBT (Named Binary Tag), o formato do level.dat.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(entries) => entries.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Tag::Byte(value) => Some(*value as i64),
+            Tag::Short(value) => Some(*value as i64),
+            Tag::Int(value) => Some(*value as i64),
+            Tag::Long(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Maior NBT aceito, já descomprimido. O level.dat tem poucos KB.
+const MAX_NBT_SIZE: u64 = 64 * 1024 * 1024;
+// Limite de Compounds e Lists aninhados, o mesmo do jogo
+const MAX_DEPTH: usize = 512;
+
+// Lê o NBT da memória, para que cada tamanho lido do arquivo possa ser conferido com
+// os bytes que sobram antes de qualquer alocação
+struct NbtReader<'a> {
+    data: &'a [u8],
+}
+
+impl NbtReader<'_> {
+    fn take(&mut self, length: usize) -> io::Result<&[u8]> {
+        if length > self.data.len() {
+            return Err(invalid(format!(
+                "Tamanho {} maior que os {} bytes restantes",
+                length,
+                self.data.len()
+            )));
+        }
+        let (taken, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    // Tamanho de uma sequência de itens com pelo menos `item_size` bytes cada
+    fn length(&mut self, item_size: usize) -> io::Result<usize> {
+        let length = i32::from_be_bytes(self.bytes()?);
+        let length = usize::try_from(length)
+            .map_err(|_| invalid(format!("Tamanho negativo: {}", length)))?;
+        if length.saturating_mul(item_size) > self.data.len() {
+            return Err(invalid(format!(
+                "{} itens não cabem nos {} bytes restantes",
+                length,
+                self.data.len()
+            )));
+        }
+        Ok(length)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let length = u16::from_be_bytes(self.bytes()?) as usize;
+        // O NBT usa o "UTF-8 modificado" do Java; para nomes de mundo o lossy basta
+        Ok(String::from_utf8_lossy(self.take(length)?).into_owned())
+    }
+
+    fn payload(&mut self, tag_type: u8, depth: usize) -> io::Result<Tag> {
+        if depth > MAX_DEPTH {
+            return Err(invalid(format!(
+                "NBT com mais de {} níveis aninhados",
+                MAX_DEPTH
+            )));
+        }
+        Ok(match tag_type {
+            1 => Tag::Byte(i8::from_be_bytes(self.bytes()?)),
+            2 => Tag::Short(i16::from_be_bytes(self.bytes()?)),
+            3 => Tag::Int(i32::from_be_bytes(self.bytes()?)),
+            4 => Tag::Long(i64::from_be_bytes(self.bytes()?)),
+            5 => Tag::Float(f32::from_be_bytes(self.bytes()?)),
+            6 => Tag::Double(f64::from_be_bytes(self.bytes()?)),
+            7 => {
+                let length = self.length(1)?;
+                Tag::ByteArray(self.take(length)?.iter().map(|byte| *byte as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let item_type = self.bytes::<1>()?[0];
+                let length = self.length(min_payload_size(item_type))?;
+                let mut items = Vec::with_capacity(length);
+                for _ in 0..length {
+                    items.push(self.payload(item_type, depth + 1)?);
+                }
+                Tag::List(items)
+            }
+            10 => {
+                let mut entries = HashMap::new();
+                loop {
+                    let entry_type = self.bytes::<1>()?[0];
+                    if entry_type == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    entries.insert(name, self.payload(entry_type, depth + 1)?);
+                }
+                Tag::Compound(entries)
+            }
+            11 => {
+                let length = self.length(4)?;
+                let mut items = Vec::with_capacity(length);
+                for _ in 0..length {
+                    items.push(i32::from_be_bytes(self.bytes()?));
+                }
+                Tag::IntArray(items)
+            }
+            12 => {
+                let length = self.length(8)?;
+                let mut items = Vec::with_capacity(length);
+                for _ in 0..length {
+                    items.push(i64::from_be_bytes(self.bytes()?));
+                }
+                Tag::LongArray(items)
+            }
+            other => return Err(invalid(format!("Tipo de tag NBT desconhecido: {}", other))),
+        })
+    }
+}
+
+// Menor tamanho do conteúdo de uma tag do tipo `tag_type`. Uma lista do tipo End
+// (0) só pode estar vazia; tipos desconhecidos falham ao ler o primeiro item.
+fn min_payload_size(tag_type: u8) -> usize {
+    match tag_type {
+        1 | 10 => 1,
+        2 | 8 => 2,
+        3 | 5 | 7 | 11 | 12 => 4,
+        4 | 6 => 8,
+        9 => 5,
+        _ => usize::MAX,
+    }
+}
+
+// Lê um NBT não comprimido, retornando a tag raiz (normalmente um Compound sem nome).
+pub fn read<R: Read>(reader: R) -> io::Result<Tag> {
+    let mut data = Vec::new();
+    reader.take(MAX_NBT_SIZE + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_NBT_SIZE {
+        return Err(invalid(format!("NBT maior que {} bytes", MAX_NBT_SIZE)));
+    }
+
+    let mut reader = NbtReader { data: &data };
+    let root_type = reader.bytes::<1>()?[0];
+    if root_type == 0 {
+        return Err(invalid("NBT vazio".to_string()));
+    }
+    reader.string()?;
+    reader.payload(root_type, 0)
+}
+
+// Lê um arquivo NBT comprimido com gzip, como o level.dat.
+pub fn read_gzip_file(path: &Path) -> io::Result<Tag> {
+    let file = File::open(path)?;
+    read(GzDecoder::new(BufReader::new(file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Raiz `{ Data: { LevelName: <name> } }`
+    fn level_dat(name: &str) -> Vec<u8> {
+        let mut data = vec![10, 0, 0, 10, 0, 4];
+        data.extend_from_slice(b"Data");
+        data.extend_from_slice(&[8, 0, 9]);
+        data.extend_from_slice(b"LevelName");
+        data.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data
+    }
+
+    fn assert_invalid(data: &[u8]) {
+        assert_eq!(read(data).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reads_level_name() {
+        let root = read(level_dat("Meu Mundo").as_slice()).unwrap();
+        assert_eq!(
+            root.get("Data")
+                .and_then(|data| data.get("LevelName"))
+                .and_then(Tag::as_str),
+            Some("Meu Mundo")
+        );
+    }
+
+    #[test]
+    fn rejects_lengths_past_the_end_of_the_input() {
+        // Byte array, int array e long array que diriam ter 2^31 - 1 itens
+        for tag_type in [7, 11, 12] {
+            let mut data = vec![10, 0, 0, tag_type, 0, 1, b'a'];
+            data.extend_from_slice(&i32::MAX.to_be_bytes());
+            assert_invalid(&data);
+        }
+        // Lista de compounds com 2^31 - 1 itens
+        let mut data = vec![10, 0, 0, 9, 0, 1, b'a', 10];
+        data.extend_from_slice(&i32::MAX.to_be_bytes());
+        assert_invalid(&data);
+        // String mais longa que o resto do arquivo
+        let mut data = level_dat("Meu Mundo");
+        data.truncate(data.len() - 6);
+        assert_invalid(&data);
+        // Lista não vazia do tipo End
+        let mut data = vec![10, 0, 0, 9, 0, 1, b'a', 0];
+        data.extend_from_slice(&1i32.to_be_bytes());
+        assert_invalid(&data);
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        // Listas de listas, cada uma com um item
+        let mut data = vec![9, 0, 0];
+        for _ in 0..=MAX_DEPTH {
+            data.push(9);
+            data.extend_from_slice(&1i32.to_be_bytes());
+        }
+        data.push(1);
+        data.extend_from_slice(&1i32.to_be_bytes());
+        data.push(0);
+        assert_invalid(&data);
+    }
+}
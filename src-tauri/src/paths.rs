@@ -0,0 +1,278 @@
+// Localização do diretório `.minecraft`, da pasta `saves` em cada plataforma e das
+// pastas de mundo dentro dela.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::enums::SavesOrigin;
+use crate::nbt;
+
+// O que interessa de cada level.dat já lido. A detecção resolve o mundo a cada
+// verificação, e o arquivo só é lido de novo quando o tamanho ou a data de
+// modificação mudam.
+static LEVEL_DAT_CACHE: Mutex<BTreeMap<PathBuf, LevelDat>> = Mutex::new(BTreeMap::new());
+
+#[derive(Clone)]
+struct LevelDat {
+    // Tamanho e data de modificação quando foi lido
+    stamp: (u64, Option<SystemTime>),
+    level_name: Option<String>,
+    last_played: i64,
+}
+
+pub struct SavesDir {
+    pub path: PathBuf,
+    pub origin: SavesOrigin,
+}
+
+pub fn default_minecraft_dirs() -> Vec<(&'static str, PathBuf)> {
+    let mut candidates = Vec::new();
+
+    if cfg!(windows) {
+        if let Some(roaming) = dirs::config_dir() {
+            candidates.push(("Windows", roaming.join(".minecraft")));
+        }
+    } else if cfg!(target_os = "macos") {
+        if let Some(application_support) = dirs::data_dir() {
+            candidates.push(("macOS", application_support.join("minecraft")));
+        }
+    } else if let Some(home) = dirs::home_dir() {
+        candidates.push(("Linux", home.join(".minecraft")));
+        candidates.push((
+            "Flatpak",
+            home.join(".var")
+                .join("app")
+                .join("com.mojang.Minecraft")
+                .join(".minecraft"),
+        ));
+    }
+
+    candidates
+}
+
+// Aceita tanto o diretório do jogo (`.minecraft`) quanto a própria pasta `saves`.
+fn saves_dir_of(minecraft_dir: &Path) -> Option<PathBuf> {
+    let saves = minecraft_dir.join("saves");
+    if saves.is_dir() {
+        return Some(saves);
+    }
+    if minecraft_dir
+        .file_name()
+        .is_some_and(|name| name == "saves")
+        && minecraft_dir.is_dir()
+    {
+        return Some(minecraft_dir.to_path_buf());
+    }
+    None
+}
+
+// Ordem de prioridade: o --gameDir detectado no processo do jogo, o diretório
+// configurado pelo usuário e, por fim, o primeiro local padrão da plataforma que
+// tiver uma pasta `saves`. Em caso de falha retorna todos os caminhos verificados.
+pub fn resolve_saves_dir(
+    game_dir: Option<&Path>,
+    override_dir: Option<&Path>,
+) -> Result<SavesDir, Vec<PathBuf>> {
+    let mut checked = Vec::new();
+
+    if let Some(game_dir) = game_dir {
+        let path = game_dir.join("saves");
+        if path.is_dir() {
+            return Ok(SavesDir {
+                path,
+                origin: SavesOrigin::GameDir,
+            });
+        }
+        checked.push(path);
+    }
+
+    if let Some(override_dir) = override_dir {
+        if let Some(path) = saves_dir_of(override_dir) {
+            return Ok(SavesDir {
+                path,
+                origin: SavesOrigin::Override,
+            });
+        }
+        checked.push(override_dir.to_path_buf());
+    }
+
+    for (label, minecraft_dir) in default_minecraft_dirs() {
+        if let Some(path) = saves_dir_of(&minecraft_dir) {
+            return Ok(SavesDir {
+                path,
+                origin: SavesOrigin::PlatformDefault(label),
+            });
+        }
+        checked.push(minecraft_dir.join("saves"));
+    }
+
+    Err(checked)
+}
+
+struct WorldFolder {
+    path: PathBuf,
+    level_name: Option<String>,
+    // O mais recente entre o LastPlayed do level.dat e o mtime do session.lock, em ms
+    last_activity: i64,
+}
+
+fn read_level_dat(level_dat: &Path) -> Option<LevelDat> {
+    let metadata = fs::metadata(level_dat).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let stamp = (metadata.len(), metadata.modified().ok());
+    let cached = LEVEL_DAT_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(level_dat)
+        .filter(|cached| cached.stamp == stamp)
+        .cloned();
+    if cached.is_some() {
+        return cached;
+    }
+
+    let root = nbt::read_gzip_file(level_dat).ok();
+    let data = root.as_ref().and_then(|root| root.get("Data"));
+    let read = LevelDat {
+        stamp,
+        level_name: data
+            .and_then(|data| data.get("LevelName"))
+            .and_then(|name| name.as_str())
+            .map(String::from),
+        last_played: data
+            .and_then(|data| data.get("LastPlayed"))
+            .and_then(|last_played| last_played.as_i64())
+            .unwrap_or(0),
+    };
+    LEVEL_DAT_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(level_dat.to_path_buf(), read.clone());
+    Some(read)
+}
+
+fn read_world_folder(path: PathBuf) -> Option<WorldFolder> {
+    let level_dat = read_level_dat(&path.join("level.dat"))?;
+    let session_lock = fs::metadata(path.join("session.lock"))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since_epoch| since_epoch.as_millis() as i64);
+
+    Some(WorldFolder {
+        path,
+        level_name: level_dat.level_name,
+        last_activity: level_dat.last_played.max(session_lock),
+    })
+}
+
+// O log informa o nome de exibição do mundo (`LevelName` no level.dat), que pode
+// ser diferente do nome da pasta (mundos renomeados, "New World" vs "New World (1)").
+// Procura a pasta cujo level.dat tem esse nome; havendo mais de uma, fica com a
+// jogada mais recentemente. Sem nenhuma correspondência, aceita uma pasta com o
+// próprio nome informado.
+pub fn resolve_world_folder(saves_dir: &Path, level_name: &str) -> Option<PathBuf> {
+    let worlds: Vec<WorldFolder> = fs::read_dir(saves_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| read_world_folder(entry.path()))
+        .collect();
+
+    worlds
+        .iter()
+        .filter(|world| world.level_name.as_deref() == Some(level_name))
+        .max_by_key(|world| world.last_activity)
+        .map(|world| world.path.clone())
+        .or_else(|| {
+            let folder = saves_dir.join(level_name);
+            folder.is_dir().then_some(folder)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    // level.dat em gzip com a raiz `{ Data: { LevelName: <name>, LastPlayed: <ms> } }`
+    fn write_level_dat(path: &Path, name: &str, last_played: i64) {
+        let mut data = vec![10, 0, 0, 10, 0, 4];
+        data.extend_from_slice(b"Data");
+        data.extend_from_slice(&[8, 0, 9]);
+        data.extend_from_slice(b"LevelName");
+        data.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(&[4, 0, 10]);
+        data.extend_from_slice(b"LastPlayed");
+        data.extend_from_slice(&last_played.to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn resolves_by_level_name_and_rereads_only_changed_level_dats() {
+        let saves =
+            std::env::temp_dir().join(format!("auto-mine-backup-paths-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&saves);
+        for folder in ["New World", "New World (1)", "Outro"] {
+            fs::create_dir_all(saves.join(folder)).unwrap();
+        }
+        write_level_dat(&saves.join("New World/level.dat"), "New World", 1_000);
+        write_level_dat(&saves.join("New World (1)/level.dat"), "New World", 2_000);
+        write_level_dat(&saves.join("Outro/level.dat"), "Mundo A", 1_000);
+
+        // O mais recente entre os que têm o nome
+        assert_eq!(
+            resolve_world_folder(&saves, "New World"),
+            Some(saves.join("New World (1)"))
+        );
+        assert_eq!(
+            resolve_world_folder(&saves, "Mundo A"),
+            Some(saves.join("Outro"))
+        );
+
+        // Mesmo tamanho e mesma data: o level.dat não é lido de novo
+        let level_dat = saves.join("Outro/level.dat");
+        let metadata = fs::metadata(&level_dat).unwrap();
+        write_level_dat(&level_dat, "Mundo B", 1_000);
+        assert_eq!(fs::metadata(&level_dat).unwrap().len(), metadata.len());
+        set_modified(&level_dat, metadata.modified().unwrap());
+        assert_eq!(
+            resolve_world_folder(&saves, "Mundo A"),
+            Some(saves.join("Outro"))
+        );
+
+        // Com outra data, o nome novo aparece
+        set_modified(
+            &level_dat,
+            metadata.modified().unwrap() + std::time::Duration::from_secs(5),
+        );
+        assert_eq!(
+            resolve_world_folder(&saves, "Mundo B"),
+            Some(saves.join("Outro"))
+        );
+        assert_eq!(resolve_world_folder(&saves, "Mundo A"), None);
+
+        let _ = fs::remove_dir_all(&saves);
+    }
+}
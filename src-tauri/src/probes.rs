@@ -0,0 +1,477 @@
+// Backends de detecção do jogo em execução.
+//
+// `check_minecraft_running` não sabe como o jogo é encontrado: ele monta um
+// `ProbeContext` com as configurações atuais e chama o `ProcessProbe` escolhido em
+// `detection_backend` no settings.json:
+//
+// - "native" (padrão): PowerShell no Windows, /proc ou ps nos outros sistemas
+// - "log": só o latest.log, sem olhar os processos
+// - "simulation": reproduz uma linha do tempo roteirizada (`simulation_script`),
+//   para testar e demonstrar os backups sem abrir o Minecraft
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+use crate::detection::{self, GameSession};
+use crate::enums::{DetectionError, DetectionStrategy, ModLoader, WorldStatus};
+use crate::launchers::Instance;
+use crate::log_watcher::LogWatcher;
+use crate::paths;
+use crate::utils::log_to_file_and_emit;
+
+pub struct ProbeContext {
+    pub instances: Vec<Instance>,
+    pub main_classes: Vec<String>,
+    pub strategy: DetectionStrategy,
+    pub log_watcher: Arc<Mutex<LogWatcher>>,
+    // `minecraft_dir` do settings.json
+    pub minecraft_dir: Option<PathBuf>,
+}
+
+// A lista tem um item por cliente aberto; vazia quando o jogo está fechado
+pub type ProbeFuture =
+    Pin<Box<dyn Future<Output = Result<Vec<GameSession>, DetectionError>> + Send>>;
+
+pub trait ProcessProbe: Send + Sync {
+    fn probe(&self, context: ProbeContext) -> ProbeFuture;
+}
+
+// Executa uma detecção bloqueante fora da thread do runtime
+fn blocking<F>(probe: F) -> ProbeFuture
+where
+    F: FnOnce() -> Result<Vec<GameSession>, DetectionError> + Send + 'static,
+{
+    Box::pin(async move {
+        tauri::async_runtime::spawn_blocking(probe)
+            .await
+            .map_err(|e| DetectionError::ProbeFailed(e.to_string()))?
+    })
+}
+
+#[cfg(windows)]
+pub struct PowerShellProbe {
+    app_handle: AppHandle,
+}
+
+#[cfg(windows)]
+impl ProcessProbe for PowerShellProbe {
+    fn probe(&self, context: ProbeContext) -> ProbeFuture {
+        use tauri_plugin_shell::ShellExt;
+
+        // O script só lista os processos; a linha de comando é interpretada no Rust
+        let pwsh_script = r#"
+            [Console]::OutputEncoding = [System.Text.Encoding]::UTF8
+            $processes = Get-CimInstance Win32_Process -Filter "Name = 'javaw.exe' OR Name = 'java.exe'" | Select-Object ProcessId, CommandLine
+            ConvertTo-Json -InputObject @($processes) -Compress
+        "#;
+
+        let command = self.app_handle.shell().command("powershell").args([
+            "-NoProfile",
+            "-Command",
+            pwsh_script,
+        ]);
+
+        Box::pin(async move {
+            let output = command.output().await.map_err(|e| {
+                DetectionError::ProbeFailed(format!("falha ao invocar shell: {}", e))
+            })?;
+
+            if !output.status.success() {
+                return Err(DetectionError::ProbeFailed(format!(
+                    "erro ao executar script PowerShell: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+
+            let processes =
+                detection::parse_process_list(String::from_utf8_lossy(&output.stdout).trim())?;
+            blocking(move || {
+                let mut log_watcher = context.log_watcher.lock().unwrap();
+                Ok(detection::probe(
+                    processes,
+                    &context.instances,
+                    &context.main_classes,
+                    &mut log_watcher,
+                    context.strategy,
+                ))
+            })
+            .await
+        })
+    }
+}
+
+#[cfg(not(windows))]
+pub struct NativeProbe;
+
+#[cfg(not(windows))]
+impl ProcessProbe for NativeProbe {
+    fn probe(&self, context: ProbeContext) -> ProbeFuture {
+        blocking(move || {
+            let processes = detection::list_java_processes()?;
+            let mut log_watcher = context.log_watcher.lock().unwrap();
+            Ok(detection::probe(
+                processes,
+                &context.instances,
+                &context.main_classes,
+                &mut log_watcher,
+                context.strategy,
+            ))
+        })
+    }
+}
+
+pub struct LogProbe;
+
+impl ProcessProbe for LogProbe {
+    fn probe(&self, context: ProbeContext) -> ProbeFuture {
+        blocking(move || {
+            let mut game_dirs: Vec<PathBuf> = context.minecraft_dir.into_iter().collect();
+            game_dirs.extend(
+                paths::default_minecraft_dirs()
+                    .into_iter()
+                    .map(|(_, dir)| dir),
+            );
+            game_dirs.extend(
+                context
+                    .instances
+                    .iter()
+                    .map(|instance| instance.game_dir.clone()),
+            );
+
+            let mut log_watcher = context.log_watcher.lock().unwrap();
+            detection::probe_logs(&game_dirs, &context.instances, &mut log_watcher)
+        })
+    }
+}
+
+// Estado de um cliente simulado, por exemplo:
+// { "status": "in_world", "world": "A", "game_dir": "/tmp/mc" }
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum ScriptedStatus {
+    Stopped,
+    MainMenu,
+    Multiplayer {
+        server: String,
+    },
+    InWorld {
+        world: String,
+        game_dir: Option<PathBuf>,
+        loader: Option<ModLoader>,
+    },
+}
+
+fn default_scripted_pid() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ScriptedSession {
+    #[serde(default = "default_scripted_pid")]
+    pid: u32,
+    #[serde(flatten)]
+    status: ScriptedStatus,
+}
+
+// Um passo tem um único cliente ou uma lista em `sessions` para simular vários
+// clientes abertos ao mesmo tempo
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum ScriptedSessions {
+    Many { sessions: Vec<ScriptedSession> },
+    One(ScriptedSession),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ScriptedStep {
+    #[serde(flatten)]
+    sessions: ScriptedSessions,
+    // Duração do passo; o último passo continua valendo depois que acaba
+    #[serde(default)]
+    seconds: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulationScript {
+    steps: Vec<ScriptedStep>,
+    // Recomeça do primeiro passo depois do último
+    #[serde(default, rename = "loop")]
+    repeat: bool,
+}
+
+pub struct SimulatedProbe {
+    script: SimulationScript,
+    // A linha do tempo começa na primeira detecção
+    started: Mutex<Option<Instant>>,
+}
+
+impl SimulatedProbe {
+    pub fn new(script: SimulationScript) -> SimulatedProbe {
+        SimulatedProbe {
+            script,
+            started: Mutex::new(None),
+        }
+    }
+
+    pub fn from_file(path: &std::path::Path) -> Result<SimulatedProbe, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let script: SimulationScript =
+            serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+        if script.steps.is_empty() {
+            return Err("o roteiro não tem nenhum passo".to_string());
+        }
+        Ok(SimulatedProbe::new(script))
+    }
+
+    fn sessions_at(&self, elapsed: Duration) -> Vec<GameSession> {
+        let total: u64 = self.script.steps.iter().map(|step| step.seconds).sum();
+        let mut elapsed = elapsed.as_secs();
+        if self.script.repeat && total > 0 {
+            elapsed %= total;
+        }
+
+        let step = self
+            .script
+            .steps
+            .iter()
+            .find(|step| {
+                if elapsed < step.seconds {
+                    return true;
+                }
+                elapsed -= step.seconds;
+                false
+            })
+            .or(self.script.steps.last());
+        let sessions = match step.map(|step| &step.sessions) {
+            Some(ScriptedSessions::Many { sessions }) => sessions.as_slice(),
+            Some(ScriptedSessions::One(session)) => std::slice::from_ref(session),
+            None => &[],
+        };
+
+        sessions
+            .iter()
+            .filter_map(|session| {
+                let (status, game_dir) = match &session.status {
+                    ScriptedStatus::Stopped => return None,
+                    ScriptedStatus::MainMenu => (WorldStatus::MainMenu, None),
+                    ScriptedStatus::Multiplayer { server } => {
+                        (WorldStatus::Multiplayer(server.clone()), None)
+                    }
+                    ScriptedStatus::InWorld {
+                        world,
+                        game_dir,
+                        loader,
+                    } => (
+                        WorldStatus::InWorld(
+                            world.clone(),
+                            game_dir.clone(),
+                            loader.unwrap_or(ModLoader::Vanilla),
+                        ),
+                        game_dir.clone(),
+                    ),
+                };
+                Some(GameSession {
+                    pid: session.pid,
+                    game_dir,
+                    status: Ok(status),
+                    instance: None,
+                    confidence: None,
+                })
+            })
+            .collect()
+    }
+}
+
+impl ProcessProbe for SimulatedProbe {
+    fn probe(&self, _context: ProbeContext) -> ProbeFuture {
+        let started = *self
+            .started
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+        let sessions = self.sessions_at(started.elapsed());
+        Box::pin(async move { Ok(sessions) })
+    }
+}
+
+#[cfg(windows)]
+fn native_probe(app_handle: &AppHandle) -> Arc<dyn ProcessProbe> {
+    Arc::new(PowerShellProbe {
+        app_handle: app_handle.clone(),
+    })
+}
+
+#[cfg(not(windows))]
+fn native_probe(_app_handle: &AppHandle) -> Arc<dyn ProcessProbe> {
+    Arc::new(NativeProbe)
+}
+
+// Escolhe o backend pelo `detection_backend` do settings.json. Lido uma vez ao
+// iniciar o app.
+pub fn from_settings(app_handle: &AppHandle) -> Arc<dyn ProcessProbe> {
+    let store = app_handle.store("settings.json").ok();
+    let setting = |key: &str| {
+        store
+            .as_ref()
+            .and_then(|store| store.get(key))
+            .and_then(|value| value.as_str().map(String::from))
+    };
+
+    match setting("detection_backend").as_deref() {
+        Some("log") => Arc::new(LogProbe),
+        Some("simulation") => {
+            let Some(script_path) = setting("simulation_script") else {
+                log_to_file_and_emit(
+                    app_handle,
+                    "[probes] simulation_script não está configurado. Usando a detecção nativa.",
+                );
+                return native_probe(app_handle);
+            };
+            match SimulatedProbe::from_file(std::path::Path::new(&script_path)) {
+                Ok(probe) => {
+                    log_to_file_and_emit(
+                        app_handle,
+                        format!("[probes] Usando simulação do roteiro: {}", script_path),
+                    );
+                    Arc::new(probe)
+                }
+                Err(e) => {
+                    log_to_file_and_emit(
+                        app_handle,
+                        format!(
+                            "[probes] Falha ao carregar o roteiro {}: {}. Usando a detecção nativa.",
+                            script_path, e
+                        ),
+                    );
+                    native_probe(app_handle)
+                }
+            }
+        }
+        _ => native_probe(app_handle),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{SessionTracker, SessionTransition};
+
+    // O roteiro de exemplo do README
+    const SCRIPT: &str = r#"{
+        "loop": false,
+        "steps": [
+            { "status": "main_menu", "seconds": 30 },
+            { "status": "in_world", "world": "A", "game_dir": "/tmp/minecraft", "seconds": 120 },
+            { "status": "in_world", "world": "B", "game_dir": "/tmp/minecraft", "seconds": 120 },
+            { "status": "stopped" }
+        ]
+    }"#;
+
+    fn probe(script: &str) -> SimulatedProbe {
+        SimulatedProbe::new(serde_json::from_str(script).unwrap())
+    }
+
+    // Pid e estado de cada cliente no segundo `seconds` do roteiro
+    fn at(probe: &SimulatedProbe, seconds: u64) -> Vec<(u32, WorldStatus)> {
+        probe
+            .sessions_at(Duration::from_secs(seconds))
+            .into_iter()
+            .map(|session| (session.pid, session.status.unwrap()))
+            .collect()
+    }
+
+    fn in_world(world: &str) -> WorldStatus {
+        WorldStatus::InWorld(
+            world.to_string(),
+            Some(PathBuf::from("/tmp/minecraft")),
+            ModLoader::Vanilla,
+        )
+    }
+
+    #[test]
+    fn follows_the_timeline() {
+        let probe = probe(SCRIPT);
+        assert_eq!(at(&probe, 0), [(1, WorldStatus::MainMenu)]);
+        assert_eq!(at(&probe, 29), [(1, WorldStatus::MainMenu)]);
+        assert_eq!(at(&probe, 30), [(1, in_world("A"))]);
+        assert_eq!(at(&probe, 149), [(1, in_world("A"))]);
+        assert_eq!(at(&probe, 150), [(1, in_world("B"))]);
+        assert_eq!(at(&probe, 270), []);
+        // O último passo continua valendo
+        assert_eq!(at(&probe, 10_000), []);
+    }
+
+    #[test]
+    fn loop_starts_over() {
+        let probe = probe(&SCRIPT.replace(r#""loop": false"#, r#""loop": true"#));
+        assert_eq!(at(&probe, 270), [(1, WorldStatus::MainMenu)]);
+        assert_eq!(at(&probe, 270 + 40), [(1, in_world("A"))]);
+        assert_eq!(at(&probe, 270 * 3 + 200), [(1, in_world("B"))]);
+    }
+
+    #[test]
+    fn steps_can_have_several_clients() {
+        let probe = probe(
+            r#"{ "steps": [
+                { "seconds": 60, "sessions": [
+                    { "pid": 1, "status": "in_world", "world": "A" },
+                    { "pid": 2, "status": "multiplayer", "server": "mc.hypixel.net" }
+                ] },
+                { "seconds": 60, "sessions": [
+                    { "pid": 2, "status": "in_world", "world": "C", "loader": "Fabric" }
+                ] }
+            ] }"#,
+        );
+        assert_eq!(
+            at(&probe, 10),
+            [
+                (
+                    1,
+                    WorldStatus::InWorld("A".to_string(), None, ModLoader::Vanilla)
+                ),
+                (2, WorldStatus::Multiplayer("mc.hypixel.net".to_string())),
+            ]
+        );
+        assert_eq!(
+            at(&probe, 70),
+            [(
+                2,
+                WorldStatus::InWorld("C".to_string(), None, ModLoader::Fabric)
+            )]
+        );
+    }
+
+    // A simulação alimentando o `SessionTracker`, como no loop rápido do main.rs:
+    // cada mundo deixado gera um backup de saída
+    #[test]
+    fn simulated_timeline_queues_exit_backups() {
+        let probe = probe(SCRIPT);
+        let mut tracker = SessionTracker::default();
+        let mut left = Vec::new();
+        for seconds in (0..=300).step_by(15) {
+            let sessions = probe.sessions_at(Duration::from_secs(seconds));
+            for transition in tracker.update(&sessions) {
+                match transition {
+                    SessionTransition::WorldLeft(world)
+                    | SessionTransition::WorldSwitched(world, _) => left.push(world.world_name),
+                    SessionTransition::WorldJoined(_) => {}
+                }
+            }
+        }
+        assert_eq!(left, ["A", "B"]);
+
+        let mut pending = Vec::new();
+        while let Some(world) = tracker.next_pending() {
+            pending.push(world.world_name);
+            tracker.finish_pending();
+        }
+        assert_eq!(pending, ["A", "B"]);
+    }
+}
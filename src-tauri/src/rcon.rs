@@ -0,0 +1,225 @@
+// Cliente RCON mínimo (protocolo Source RCON usado pelo servidor Java).
+//
+// Cada pacote é: tamanho (i32), id (i32), tipo (i32), corpo terminado em zero e um
+// zero extra; todos os inteiros em little-endian.
+
+use std::io;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+const PACKET_AUTH: i32 = 3;
+const PACKET_COMMAND: i32 = 2;
+const PACKET_AUTH_RESPONSE: i32 = 2;
+const PACKET_RESPONSE: i32 = 0;
+
+// Limite do servidor para o corpo de um pacote enviado pelo cliente
+const MAX_REQUEST_BODY: usize = 1446;
+const MAX_PACKET_LENGTH: i32 = 4096 + 10;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct RconClient {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+async fn with_timeout<T>(
+    duration: Duration,
+    future: impl std::future::Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    timeout(duration, future).await.map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("sem resposta em {}s", duration.as_secs()),
+        )
+    })?
+}
+
+impl RconClient {
+    // Conecta e autentica. Falha com PermissionDenied se a senha for recusada.
+    pub async fn connect(address: &str, password: &str) -> io::Result<RconClient> {
+        let stream = with_timeout(CONNECT_TIMEOUT, TcpStream::connect(address)).await?;
+        let mut client = RconClient { stream, next_id: 1 };
+
+        let id = client.send(PACKET_AUTH, password).await?;
+        with_timeout(AUTH_TIMEOUT, async {
+            loop {
+                let (response_id, kind, _) = client.receive().await?;
+                if kind != PACKET_AUTH_RESPONSE {
+                    continue;
+                }
+                if response_id == -1 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "senha do RCON recusada",
+                    ));
+                }
+                if response_id == id {
+                    return Ok(());
+                }
+            }
+        })
+        .await?;
+
+        Ok(client)
+    }
+
+    // Executa um comando e retorna a resposta do servidor. Comandos como
+    // `save-all flush` só respondem depois de terminar, então o tempo limite é
+    // escolhido por quem chama.
+    pub async fn command(&mut self, command: &str, limit: Duration) -> io::Result<String> {
+        let id = self.send(PACKET_COMMAND, command).await?;
+        with_timeout(limit, async {
+            loop {
+                let (response_id, kind, body) = self.receive().await?;
+                if kind == PACKET_RESPONSE && response_id == id {
+                    return Ok(body);
+                }
+            }
+        })
+        .await
+    }
+
+    async fn send(&mut self, kind: i32, body: &str) -> io::Result<i32> {
+        if body.len() > MAX_REQUEST_BODY {
+            return Err(invalid(format!(
+                "comando RCON muito longo ({} bytes)",
+                body.len()
+            )));
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let length = (4 + 4 + body.len() + 2) as i32;
+        let mut packet = Vec::with_capacity(4 + length as usize);
+        packet.extend_from_slice(&length.to_le_bytes());
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&kind.to_le_bytes());
+        packet.extend_from_slice(body.as_bytes());
+        packet.extend_from_slice(&[0, 0]);
+
+        self.stream.write_all(&packet).await?;
+        Ok(id)
+    }
+
+    async fn receive(&mut self) -> io::Result<(i32, i32, String)> {
+        let length = self.stream.read_i32_le().await?;
+        if !(10..=MAX_PACKET_LENGTH).contains(&length) {
+            return Err(invalid(format!(
+                "pacote RCON com tamanho inválido: {}",
+                length
+            )));
+        }
+
+        let mut payload = vec![0u8; length as usize];
+        self.stream.read_exact(&mut payload).await?;
+
+        let id = i32::from_le_bytes(payload[0..4].try_into().unwrap());
+        let kind = i32::from_le_bytes(payload[4..8].try_into().unwrap());
+        let body = &payload[8..payload.len() - 2];
+        Ok((id, kind, String::from_utf8_lossy(body).into_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const PASSWORD: &str = "segredo";
+
+    async fn read_packet(stream: &mut TcpStream) -> io::Result<(i32, i32, String)> {
+        let length = stream.read_i32_le().await?;
+        let mut payload = vec![0u8; length as usize];
+        stream.read_exact(&mut payload).await?;
+        let id = i32::from_le_bytes(payload[0..4].try_into().unwrap());
+        let kind = i32::from_le_bytes(payload[4..8].try_into().unwrap());
+        let body = String::from_utf8_lossy(&payload[8..payload.len() - 2]).into_owned();
+        Ok((id, kind, body))
+    }
+
+    async fn write_packet(stream: &mut TcpStream, id: i32, kind: i32, body: &str) {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&((10 + body.len()) as i32).to_le_bytes());
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&kind.to_le_bytes());
+        packet.extend_from_slice(body.as_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        stream.write_all(&packet).await.unwrap();
+    }
+
+    // Servidor RCON de teste: aceita uma conexão, recusa senhas erradas com o id -1 e
+    // responde cada comando com "<comando>: ok", mandando antes uma resposta com outro
+    // id (de um comando antigo) que o cliente precisa ignorar
+    async fn start_stub() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            while let Ok((id, kind, body)) = read_packet(&mut stream).await {
+                match kind {
+                    PACKET_AUTH if body == PASSWORD => {
+                        write_packet(&mut stream, id, PACKET_RESPONSE, "").await;
+                        write_packet(&mut stream, id, PACKET_AUTH_RESPONSE, "").await;
+                    }
+                    PACKET_AUTH => {
+                        write_packet(&mut stream, -1, PACKET_AUTH_RESPONSE, "").await;
+                    }
+                    _ => {
+                        write_packet(&mut stream, id + 100, PACKET_RESPONSE, "atrasada").await;
+                        let response = format!("{}: ok", body);
+                        write_packet(&mut stream, id, PACKET_RESPONSE, &response).await;
+                    }
+                }
+            }
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn authenticates_and_runs_commands() {
+        let address = start_stub().await;
+        let mut client = RconClient::connect(&address, PASSWORD).await.unwrap();
+        let limit = Duration::from_secs(5);
+        assert_eq!(
+            client.command("save-off", limit).await.unwrap(),
+            "save-off: ok"
+        );
+        assert_eq!(
+            client.command("save-all flush", limit).await.unwrap(),
+            "save-all flush: ok"
+        );
+        assert_eq!(
+            client.command("save-on", limit).await.unwrap(),
+            "save-on: ok"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_password() {
+        let address = start_stub().await;
+        let error = RconClient::connect(&address, "errada").await.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn refuses_commands_over_the_size_limit() {
+        let address = start_stub().await;
+        let mut client = RconClient::connect(&address, PASSWORD).await.unwrap();
+        let command = "say ".to_string() + &"a".repeat(MAX_REQUEST_BODY);
+        let error = client
+            .command(&command, Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
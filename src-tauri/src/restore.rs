@@ -0,0 +1,429 @@
+// Restauração de um backup para uma pasta (saves ou a pasta do servidor).
+//
+// O formato vem do registro do backup (`backups.json`) ou, sem registro, do nome.
+// O manifesto diz para quais pastas o backup volta: uma por mundo ou dimensão, ou
+// uma só quando o mundo está na raiz do arquivo. Backups de versões anteriores sem
+// manifesto são a própria pasta do mundo e voltam para uma pasta com o nome do
+// mundo, tirado do nome do backup (`backup_<mundo>_<AAAAMMDD>_<HHMMSS>`).
+//
+// Nada é sobrescrito: o conteúdo é extraído numa pasta temporária e as pastas que já
+// existem no destino são renomeadas para `<pasta>_antes_da_restauracao_<data>`.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::catalog;
+use crate::enums::ArchiveFormat;
+use crate::integrity::HASHES_NAME;
+use crate::repository;
+use crate::session_lock::{self, LockState};
+use crate::world_group::{GroupManifest, MANIFEST_NAME};
+
+// Nome do mundo em `backup_<mundo>_<AAAAMMDD>_<HHMMSS>`
+fn world_from_backup_name(name: &str) -> Option<String> {
+    let rest = name.strip_prefix("backup_")?;
+    let mut parts = rest.rsplitn(3, '_');
+    let time = parts.next()?;
+    let date = parts.next()?;
+    let world = parts.next()?;
+    let is_digits =
+        |value: &str, len: usize| value.len() == len && value.chars().all(|c| c.is_ascii_digit());
+    (is_digits(date, 8) && is_digits(time, 6) && !world.is_empty()).then(|| world.to_string())
+}
+
+fn is_plain_folder(folder: &str) -> bool {
+    let mut components = Path::new(folder).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    )
+}
+
+// Restaura o backup em `destination` e retorna as pastas restauradas. Num backup em
+// pasta, `backup_path` pode ser a pasta ou o manifesto dentro dela.
+pub fn restore_backup(backup_path: &Path, destination: &Path) -> Result<Vec<PathBuf>, String> {
+    let backup_path = match backup_path.file_name() {
+        Some(name) if name == MANIFEST_NAME => backup_path.parent().unwrap_or(backup_path),
+        _ => backup_path,
+    };
+    let format = catalog::find(backup_path)
+        .map(|record| record.format)
+        .or_else(|| catalog::infer_format(backup_path))
+        .ok_or_else(|| format!("{:?} não é um backup reconhecido", backup_path))?;
+    let backup_name = backup_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let staging = destination.join(format!(".auto-mine-backup-restore_{}", timestamp));
+    let contents = staging.join("contents");
+    let restored = extract(format, backup_path, &contents)
+        .map_err(|e| format!("falha ao extrair {:?}: {}", backup_path, e))
+        .and_then(|_| {
+            place_folders(
+                &contents,
+                catalog::strip_extension(&backup_name, format),
+                destination,
+                &timestamp.to_string(),
+            )
+        });
+    let _ = fs::remove_dir_all(&staging);
+    restored
+}
+
+fn place_folders(
+    contents: &Path,
+    backup_name: &str,
+    destination: &Path,
+    timestamp: &str,
+) -> Result<Vec<PathBuf>, String> {
+    // A lista de hashes é do backup, não do mundo
+    let _ = fs::remove_file(contents.join(HASHES_NAME));
+    let manifest_path = contents.join(MANIFEST_NAME);
+    // Sem manifesto, o conteúdo inteiro é a pasta de um mundo
+    let sources: Vec<(String, PathBuf)> = if manifest_path.is_file() {
+        let manifest: GroupManifest = fs::read(&manifest_path)
+            .ok()
+            .and_then(|manifest| serde_json::from_slice(&manifest).ok())
+            .ok_or_else(|| "manifesto do backup inválido".to_string())?;
+        if manifest.at_root {
+            // O manifesto não faz parte do mundo
+            let _ = fs::remove_file(&manifest_path);
+            match <[String; 1]>::try_from(manifest.folders) {
+                Ok([folder]) => vec![(folder, contents.to_path_buf())],
+                Err(folders) => {
+                    return Err(format!(
+                        "manifesto do backup inválido: um mundo na raiz com as pastas {:?}",
+                        folders
+                    ))
+                }
+            }
+        } else {
+            manifest
+                .folders
+                .into_iter()
+                .map(|folder| {
+                    let source = contents.join(&folder);
+                    (folder, source)
+                })
+                .collect()
+        }
+    } else {
+        let world = world_from_backup_name(backup_name).ok_or_else(|| {
+            format!(
+                "não foi possível descobrir o nome do mundo pelo nome do backup {:?}",
+                backup_name
+            )
+        })?;
+        vec![(world, contents.to_path_buf())]
+    };
+    if sources.is_empty() || !sources.iter().all(|(folder, _)| is_plain_folder(folder)) {
+        let folders: Vec<&String> = sources.iter().map(|(folder, _)| folder).collect();
+        return Err(format!("pastas inválidas no backup: {:?}", folders));
+    }
+
+    for (folder, _) in &sources {
+        let lock = destination.join(folder).join("session.lock");
+        if session_lock::lock_state(&lock) == LockState::Held {
+            return Err(format!(
+                "o mundo {:?} está aberto; feche o jogo ou o servidor antes de restaurar",
+                destination.join(folder)
+            ));
+        }
+    }
+
+    let mut restored = Vec::new();
+    for (folder, source) in sources {
+        let target = destination.join(&folder);
+        if target.exists() {
+            let aside = destination.join(format!("{}_antes_da_restauracao_{}", folder, timestamp));
+            fs::rename(&target, &aside)
+                .map_err(|e| format!("falha ao mover {:?} para {:?}: {}", target, aside, e))?;
+        }
+        if source.exists() {
+            fs::rename(&source, &target)
+                .map_err(|e| format!("falha ao mover {:?} para {:?}: {}", source, target, e))?;
+        } else {
+            // Pasta vazia no momento do backup
+            fs::create_dir_all(&target)
+                .map_err(|e| format!("falha ao criar {:?}: {}", target, e))?;
+        }
+        restored.push(target);
+    }
+    Ok(restored)
+}
+
+fn extract(format: ArchiveFormat, backup_path: &Path, contents: &Path) -> io::Result<()> {
+    fs::create_dir_all(contents)?;
+    match format {
+        ArchiveFormat::Zip | ArchiveFormat::McWorld => extract_zip(backup_path, contents),
+        ArchiveFormat::TarZst => extract_tar_zst(backup_path, contents),
+        ArchiveFormat::Folder => copy_folder(backup_path, contents),
+        ArchiveFormat::Repository => repository::export_folder(backup_path, contents),
+    }
+}
+
+fn extract_zip(backup_path: &Path, contents: &Path) -> io::Result<()> {
+    let mut archive = zip::ZipArchive::new(File::open(backup_path)?)?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let Some(name) = entry.enclosed_name() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("caminho inválido no backup: {}", entry.name()),
+            ));
+        };
+        let target = contents.join(name);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut File::create(&target)?)?;
+    }
+    Ok(())
+}
+
+fn extract_tar_zst(backup_path: &Path, contents: &Path) -> io::Result<()> {
+    let mut decoder = zstd::Decoder::new(File::open(backup_path)?)?;
+    // Backups com `zstd_long_distance` podem usar uma janela maior que o padrão
+    decoder.window_log_max(31)?;
+    let mut archive = tar::Archive::new(decoder);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        // `unpack_in` recusa caminhos que sairiam da pasta de destino
+        if !entry.unpack_in(contents)? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("caminho inválido no backup: {:?}", entry.path()?),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// O backup em pasta é copiado (e não movido) para continuar disponível
+fn copy_folder(backup_path: &Path, contents: &Path) -> io::Result<()> {
+    for entry in WalkDir::new(backup_path) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(backup_path).unwrap();
+        let target = contents.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    use crate::archive::{self, ArchiveSettings};
+    use crate::enums::CompressionMethod;
+    use crate::world_group::WorldGroup;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "auto-mine-backup-restore-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn settings(format: ArchiveFormat) -> ArchiveSettings {
+        ArchiveSettings {
+            format,
+            threads: 1,
+            zstd_level: 3,
+            zstd_long_distance: false,
+            zip_method: CompressionMethod::Deflate,
+            zip_level: None,
+            stored_extensions: Vec::new(),
+        }
+    }
+
+    // Pastas de um servidor Paper, cada uma com um arquivo próprio
+    fn paper_server(root: &Path) {
+        for folder in ["world", "world_nether", "world_the_end"] {
+            fs::create_dir_all(root.join(folder).join("region")).unwrap();
+            fs::write(root.join(folder).join("level.dat"), folder).unwrap();
+            fs::write(
+                root.join(folder).join("region/r.0.0.mca"),
+                format!("região de {}", folder),
+            )
+            .unwrap();
+        }
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn reads_the_world_from_legacy_backup_names() {
+        assert_eq!(
+            world_from_backup_name("backup_Meu_Mundo_20250101_120000").as_deref(),
+            Some("Meu_Mundo")
+        );
+        assert_eq!(world_from_backup_name("backup_world_2025_120000"), None);
+        assert_eq!(world_from_backup_name("backup__20250101_120000"), None);
+        assert_eq!(world_from_backup_name("world_20250101_120000"), None);
+    }
+
+    #[test]
+    fn paper_group_round_trips() {
+        for (format, extension) in [
+            (ArchiveFormat::Zip, ".zip"),
+            (ArchiveFormat::TarZst, ".tar.zst"),
+            (ArchiveFormat::Folder, ""),
+        ] {
+            let dir = temp_dir(&format!("paper-{}", format));
+            let server = dir.join("server");
+            paper_server(&server);
+            let group = WorldGroup::with_dimensions("server_server_world", &server, "world");
+            assert_eq!(group.folders.len(), 3);
+            let backup = dir.join(format!(
+                "backup_server_server_world_20260101_120000{}",
+                extension
+            ));
+            archive::write_world_group(&group, &backup, &settings(format), &|_| {}).unwrap();
+
+            let destination = dir.join("restored");
+            fs::create_dir_all(&destination).unwrap();
+            let restored = restore_backup(&backup, &destination).unwrap();
+            assert_eq!(
+                restored,
+                group
+                    .folders
+                    .iter()
+                    .map(|folder| destination.join(folder))
+                    .collect::<Vec<_>>()
+            );
+            for folder in &group.folders {
+                for file in ["level.dat", "region/r.0.0.mca"] {
+                    assert_eq!(
+                        fs::read(destination.join(folder).join(file)).unwrap(),
+                        fs::read(server.join(folder).join(file)).unwrap(),
+                        "{} {}",
+                        folder,
+                        file
+                    );
+                }
+            }
+            // O manifesto e a lista de hashes não voltam para as pastas do mundo
+            assert!(!destination.join(MANIFEST_NAME).exists());
+            assert!(!destination.join("world").join(HASHES_NAME).exists());
+
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn single_world_at_root_uses_the_manifest_folder() {
+        let dir = temp_dir("at-root");
+        let server = dir.join("server");
+        paper_server(&server);
+        let group = WorldGroup::single("server_server_world", &server.join("world"));
+        let backup = dir.join("backup_server_server_world_20260101_120000.zip");
+        archive::write_world_group(&group, &backup, &settings(ArchiveFormat::Zip), &|_| {})
+            .unwrap();
+
+        let destination = dir.join("restored");
+        fs::create_dir_all(&destination).unwrap();
+        let restored = restore_backup(&backup, &destination).unwrap();
+        assert_eq!(restored, vec![destination.join("world")]);
+        assert_eq!(
+            fs::read(destination.join("world/level.dat")).unwrap(),
+            b"world"
+        );
+        assert!(!destination.join("world").join(MANIFEST_NAME).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn legacy_zip_restores_to_the_world_in_its_name() {
+        let dir = temp_dir("legacy");
+        // Backup de uma versão anterior: a pasta do mundo na raiz, sem manifesto
+        let backup = dir.join("backup_Meu_Mundo_20250101_120000.zip");
+        write_zip(
+            &backup,
+            &[("level.dat", b"level"), ("region/r.0.0.mca", b"region")],
+        );
+        let destination = dir.join("saves");
+        fs::create_dir_all(destination.join("Meu_Mundo")).unwrap();
+        fs::write(destination.join("Meu_Mundo/level.dat"), b"atual").unwrap();
+
+        let restored = restore_backup(&backup, &destination).unwrap();
+        assert_eq!(restored, vec![destination.join("Meu_Mundo")]);
+        assert_eq!(
+            fs::read(destination.join("Meu_Mundo/region/r.0.0.mca")).unwrap(),
+            b"region"
+        );
+        // O mundo que estava lá fica ao lado
+        let aside: Vec<String> = fs::read_dir(&destination)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("Meu_Mundo_antes_da_restauracao_"))
+            .collect();
+        assert_eq!(aside.len(), 1);
+        assert_eq!(
+            fs::read(destination.join(&aside[0]).join("level.dat")).unwrap(),
+            b"atual"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_destination() {
+        let dir = temp_dir("traversal");
+        let destination = dir.join("saves/inner");
+        fs::create_dir_all(&destination).unwrap();
+
+        for (index, name) in ["../evil.txt", "/evil.txt"].iter().enumerate() {
+            let backup = dir.join(format!("backup_world_2025010{}_120000.zip", index + 1));
+            write_zip(&backup, &[("level.dat", b"level"), (name, b"evil")]);
+            let error = restore_backup(&backup, &destination).unwrap_err();
+            assert!(error.contains("caminho inválido"), "{}", error);
+        }
+
+        // Pastas do manifesto que sairiam do destino
+        let backup = dir.join("backup_world_20250103_120000.zip");
+        write_zip(
+            &backup,
+            &[(
+                MANIFEST_NAME,
+                br#"{"world":"world","folders":["../world"]}"#,
+            )],
+        );
+        let error = restore_backup(&backup, &destination).unwrap_err();
+        assert!(error.contains("pastas inválidas"), "{}", error);
+
+        assert!(!dir.join("saves/evil.txt").exists());
+        assert!(!dir.join("saves/world").exists());
+        assert_eq!(fs::read_dir(&destination).unwrap().count(), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
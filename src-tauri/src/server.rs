@@ -0,0 +1,186 @@
+// Servidores dedicados do Minecraft Java configurados em `server_dirs`.
+//
+// O mundo vem do `level-name` do server.properties e as credenciais do RCON de
+// `enable-rcon`, `rcon.port` e `rcon.password`. O servidor é considerado em execução
+// enquanto segura o session.lock do mundo.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+use crate::session_lock::{self, LockState};
+use crate::world_group::WorldGroup;
+
+const DEFAULT_LEVEL_NAME: &str = "world";
+const DEFAULT_RCON_PORT: u16 = 25575;
+const LOCAL_HOST: &str = "127.0.0.1";
+
+#[derive(Debug, Clone)]
+pub struct RconSettings {
+    pub address: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DedicatedServer {
+    pub dir: PathBuf,
+    pub level_name: String,
+    // None quando o RCON está desativado ou sem senha
+    pub rcon: Option<RconSettings>,
+}
+
+impl DedicatedServer {
+    pub fn world_dir(&self) -> PathBuf {
+        self.dir.join(&self.level_name)
+    }
+
+    // Nome usado na pasta e nos arquivos de backup; inclui a pasta do servidor para
+    // não misturar com mundos do cliente de mesmo nome (todo servidor tem um "world")
+    pub fn backup_name(&self) -> String {
+        let server_name = self
+            .dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "server".to_string());
+        format!("server_{}_{}", server_name, self.level_name)
+    }
+
+    // O mundo e, no Bukkit/Spigot/Paper, as pastas `_nether` e `_the_end` ao lado dele
+    pub fn world_group(&self) -> WorldGroup {
+        WorldGroup::with_dimensions(&self.backup_name(), &self.dir, &self.level_name)
+    }
+
+    pub fn is_running(&self) -> bool {
+        session_lock::lock_state(&self.world_dir().join("session.lock")) == LockState::Held
+    }
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                if let Some(c) = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    result.push(c);
+                }
+            }
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+    result
+}
+
+// Lê um arquivo .properties do Java (chave=valor, comentários com # ou !).
+pub fn read_properties(path: &Path) -> io::Result<HashMap<String, String>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .map(str::trim_start)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+        .map(|line| {
+            // O separador é o primeiro `=` ou `:` que não está escapado
+            let mut escaped = false;
+            let separator = line.char_indices().find(|(_, c)| {
+                let is_separator = !escaped && (*c == '=' || *c == ':');
+                escaped = !escaped && *c == '\\';
+                is_separator
+            });
+            let (key, value) = match separator {
+                Some((index, _)) => (&line[..index], &line[index + 1..]),
+                None => (line, ""),
+            };
+            (unescape(key.trim()), unescape(value.trim_start()))
+        })
+        .collect())
+}
+
+// Um server-ip específico também limita onde o RCON escuta. Sem ele, ou com um
+// endereço que escuta em todas as interfaces (`0.0.0.0`, `::`), a conexão vai para o
+// próprio computador.
+fn rcon_address(server_ip: Option<&str>, port: u16) -> String {
+    let host = server_ip.unwrap_or(LOCAL_HOST);
+    match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) if ip.is_unspecified() => format!("{}:{}", LOCAL_HOST, port),
+        // Endereços IPv6 vão entre colchetes
+        Ok(ip) => SocketAddr::new(ip, port).to_string(),
+        Err(_) => format!("{}:{}", host, port),
+    }
+}
+
+pub fn load_server(dir: &Path) -> Result<DedicatedServer, String> {
+    let properties = read_properties(&dir.join("server.properties"))
+        .map_err(|e| format!("falha ao ler server.properties em {:?}: {}", dir, e))?;
+    let property = |key: &str| {
+        properties
+            .get(key)
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    };
+
+    let rcon = match (property("enable-rcon"), property("rcon.password")) {
+        (Some("true"), Some(password)) => {
+            let port = property("rcon.port")
+                .and_then(|port| port.parse::<u16>().ok())
+                .unwrap_or(DEFAULT_RCON_PORT);
+            Some(RconSettings {
+                address: rcon_address(property("server-ip"), port),
+                password: password.to_string(),
+            })
+        }
+        _ => None,
+    };
+
+    Ok(DedicatedServer {
+        dir: dir.to_path_buf(),
+        level_name: property("level-name")
+            .unwrap_or(DEFAULT_LEVEL_NAME)
+            .to_string(),
+        rcon,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rcon_connects_locally_when_the_server_listens_everywhere() {
+        for server_ip in [None, Some("0.0.0.0"), Some("::"), Some("[::]")] {
+            assert_eq!(rcon_address(server_ip, 25575), "127.0.0.1:25575");
+        }
+        assert_eq!(
+            rcon_address(Some("192.168.0.10"), 25575),
+            "192.168.0.10:25575"
+        );
+        assert_eq!(rcon_address(Some("::1"), 25575), "[::1]:25575");
+        assert_eq!(rcon_address(Some("mc.local"), 25576), "mc.local:25576");
+    }
+
+    #[test]
+    fn empty_server_ip_uses_the_local_host() {
+        let dir =
+            std::env::temp_dir().join(format!("auto-mine-backup-server-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("server.properties"),
+            "server-ip=\nenable-rcon=true\nrcon.password=segredo\nrcon.port=25580\n",
+        )
+        .unwrap();
+        let rcon = load_server(&dir).unwrap().rcon.unwrap();
+        assert_eq!(rcon.address, "127.0.0.1:25580");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
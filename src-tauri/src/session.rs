@@ -0,0 +1,306 @@
+// Acompanhamento das sessões de jogo entre uma verificação e outra.
+//
+// O `SessionTracker` guarda o mundo local aberto em cada cliente (pelo pid) e
+// compara com cada nova lista de `GameSession`s, emitindo uma `SessionTransition`
+// quando o jogador entra, sai ou troca de mundo num cliente. Toda saída (voltar ao
+// menu, entrar num servidor, fechar o jogo ou trocar de mundo) coloca o mundo
+// deixado na fila de backups de saída pendentes.
+//
+// As sessões abertas e a fila ficam salvas no settings.json (`active_sessions` e
+// `pending_backups`), assim nenhum backup de saída se perde se o app for reiniciado
+// com o jogo aberto.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::Wry;
+use tauri_plugin_store::Store;
+
+use crate::detection::GameSession;
+use crate::enums::{ModLoader, WorldStatus};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveWorld {
+    pub pid: u32,
+    pub world_name: String,
+    pub game_dir: Option<PathBuf>,
+    pub loader: ModLoader,
+}
+
+impl ActiveWorld {
+    fn is_same(&self, other: &ActiveWorld) -> bool {
+        self.world_name == other.world_name && self.game_dir == other.game_dir
+    }
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum SessionTransition {
+    WorldJoined(ActiveWorld),
+    WorldLeft(ActiveWorld),
+    // Mundo deixado e mundo novo
+    WorldSwitched(ActiveWorld, ActiveWorld),
+}
+
+#[derive(Default)]
+pub struct SessionTracker {
+    active: HashMap<u32, ActiveWorld>,
+    pending: VecDeque<ActiveWorld>,
+}
+
+fn load<T: for<'de> Deserialize<'de>>(store: &Arc<Store<Wry>>, key: &str) -> Vec<T> {
+    store
+        .get(key)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+impl SessionTracker {
+    // Retoma as sessões e a fila de backups da última execução do app
+    pub fn restore(store: &Arc<Store<Wry>>) -> SessionTracker {
+        let active: Vec<ActiveWorld> = load(store, "active_sessions");
+        let mut pending: VecDeque<ActiveWorld> = load(store, "pending_backups").into();
+
+        // Versões anteriores guardavam um único mundo pendente
+        if let Some(world_name) = store
+            .get("last_backed_up_world")
+            .and_then(|world| world.as_str().map(String::from))
+        {
+            pending.push_back(ActiveWorld {
+                pid: 0,
+                world_name,
+                game_dir: store
+                    .get("last_backed_up_game_dir")
+                    .and_then(|dir| dir.as_str().map(PathBuf::from)),
+                loader: ModLoader::Unknown,
+            });
+            store.delete("last_backed_up_world");
+            store.delete("last_backed_up_game_dir");
+        }
+
+        SessionTracker {
+            active: active.into_iter().map(|world| (world.pid, world)).collect(),
+            pending,
+        }
+    }
+
+    pub fn update(&mut self, sessions: &[GameSession]) -> Vec<SessionTransition> {
+        let mut transitions = Vec::new();
+
+        for session in sessions {
+            // Sem saber o estado do cliente, mantém o que se sabia dele
+            let Ok(status) = &session.status else {
+                continue;
+            };
+            let next = match status {
+                WorldStatus::InWorld(world_name, game_dir, loader) => Some(ActiveWorld {
+                    pid: session.pid,
+                    world_name: world_name.clone(),
+                    game_dir: game_dir.clone(),
+                    loader: *loader,
+                }),
+                WorldStatus::MainMenu | WorldStatus::Multiplayer(_) => None,
+            };
+
+            match (self.active.remove(&session.pid), next) {
+                (None, None) => {}
+                (None, Some(joined)) => {
+                    self.active.insert(session.pid, joined.clone());
+                    transitions.push(SessionTransition::WorldJoined(joined));
+                }
+                (Some(left), None) => {
+                    self.pending.push_back(left.clone());
+                    transitions.push(SessionTransition::WorldLeft(left));
+                }
+                (Some(left), Some(joined)) if left.is_same(&joined) => {
+                    self.active.insert(session.pid, joined);
+                }
+                (Some(left), Some(joined)) => {
+                    self.active.insert(session.pid, joined.clone());
+                    self.pending.push_back(left.clone());
+                    transitions.push(SessionTransition::WorldSwitched(left, joined));
+                }
+            }
+        }
+
+        // Clientes que não aparecem mais foram fechados
+        let running: HashSet<u32> = sessions.iter().map(|session| session.pid).collect();
+        let mut closed: Vec<u32> = self
+            .active
+            .keys()
+            .filter(|pid| !running.contains(pid))
+            .copied()
+            .collect();
+        closed.sort_unstable();
+        for pid in closed {
+            if let Some(left) = self.active.remove(&pid) {
+                self.pending.push_back(left.clone());
+                transitions.push(SessionTransition::WorldLeft(left));
+            }
+        }
+
+        transitions
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // Próximo backup de saída pendente. Só sai da fila com `finish_pending`, assim
+    // um backup interrompido é refeito na próxima execução do app.
+    pub fn next_pending(&self) -> Option<ActiveWorld> {
+        self.pending.front().cloned()
+    }
+
+    pub fn finish_pending(&mut self) {
+        self.pending.pop_front();
+    }
+
+    pub fn persist(&self, store: &Arc<Store<Wry>>) {
+        let mut active: Vec<&ActiveWorld> = self.active.values().collect();
+        active.sort_by_key(|world| world.pid);
+        store.set("active_sessions", serde_json::json!(active));
+        store.set("pending_backups", serde_json::json!(self.pending));
+    }
+}
+
+// Se há algum cliente do Minecraft ou servidor dedicado aberto, segundo a última
+// verificação. Usado para deixar a compactação mais leve enquanto se joga.
+#[derive(Default)]
+pub struct GameActivity(AtomicBool);
+
+impl GameActivity {
+    pub fn set_running(&self, running: bool) {
+        self.0.store(running, Ordering::Relaxed);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::DetectionError;
+
+    fn session(pid: u32, status: Result<WorldStatus, DetectionError>) -> GameSession {
+        GameSession {
+            pid,
+            game_dir: None,
+            status,
+            instance: None,
+            confidence: None,
+        }
+    }
+
+    fn in_world(pid: u32, world: &str) -> GameSession {
+        session(
+            pid,
+            Ok(WorldStatus::InWorld(
+                world.to_string(),
+                None,
+                ModLoader::Vanilla,
+            )),
+        )
+    }
+
+    // Resumo das transições, para comparar
+    fn describe(transitions: &[SessionTransition]) -> Vec<String> {
+        transitions
+            .iter()
+            .map(|transition| match transition {
+                SessionTransition::WorldJoined(world) => {
+                    format!("{} entrou em {}", world.pid, world.world_name)
+                }
+                SessionTransition::WorldLeft(world) => {
+                    format!("{} saiu de {}", world.pid, world.world_name)
+                }
+                SessionTransition::WorldSwitched(left, joined) => format!(
+                    "{} trocou {} por {}",
+                    left.pid, left.world_name, joined.world_name
+                ),
+            })
+            .collect()
+    }
+
+    fn pending(tracker: &mut SessionTracker) -> Vec<String> {
+        let mut worlds = Vec::new();
+        while let Some(world) = tracker.next_pending() {
+            worlds.push(world.world_name);
+            tracker.finish_pending();
+        }
+        worlds
+    }
+
+    #[test]
+    fn tracks_join_switch_and_leave() {
+        let mut tracker = SessionTracker::default();
+        assert!(describe(&tracker.update(&[session(1, Ok(WorldStatus::MainMenu))])).is_empty());
+
+        assert_eq!(
+            describe(&tracker.update(&[in_world(1, "A")])),
+            ["1 entrou em A"]
+        );
+        assert!(!tracker.has_pending());
+        // Continuar no mesmo mundo não muda nada
+        assert!(describe(&tracker.update(&[in_world(1, "A")])).is_empty());
+
+        assert_eq!(
+            describe(&tracker.update(&[in_world(1, "B")])),
+            ["1 trocou A por B"]
+        );
+        assert_eq!(
+            describe(&tracker.update(&[session(1, Ok(WorldStatus::MainMenu))])),
+            ["1 saiu de B"]
+        );
+        assert_eq!(pending(&mut tracker), ["A", "B"]);
+    }
+
+    #[test]
+    fn multiplayer_counts_as_leaving_the_world() {
+        let mut tracker = SessionTracker::default();
+        tracker.update(&[in_world(1, "A")]);
+        assert_eq!(
+            describe(&tracker.update(&[session(
+                1,
+                Ok(WorldStatus::Multiplayer("mc.hypixel.net".to_string()))
+            )])),
+            ["1 saiu de A"]
+        );
+    }
+
+    #[test]
+    fn vanished_process_leaves_its_world() {
+        let mut tracker = SessionTracker::default();
+        tracker.update(&[in_world(1, "A"), in_world(2, "C")]);
+        assert_eq!(
+            describe(&tracker.update(&[in_world(1, "A")])),
+            ["2 saiu de C"]
+        );
+        assert_eq!(describe(&tracker.update(&[])), ["1 saiu de A"]);
+        assert_eq!(pending(&mut tracker), ["C", "A"]);
+    }
+
+    #[test]
+    fn detection_error_does_not_trigger_exit_backup() {
+        let mut tracker = SessionTracker::default();
+        tracker.update(&[in_world(1, "A")]);
+
+        let failed = session(
+            1,
+            Err(DetectionError::QuickPlayUnreadable(
+                "quickPlay/log.json".to_string(),
+            )),
+        );
+        assert!(describe(&tracker.update(&[failed])).is_empty());
+        assert!(!tracker.has_pending());
+
+        // O mundo continua aberto depois que a detecção volta
+        assert!(describe(&tracker.update(&[in_world(1, "A")])).is_empty());
+        assert!(!tracker.has_pending());
+    }
+}
@@ -0,0 +1,123 @@
+// Detecção do mundo aberto pelo session.lock.
+//
+// Enquanto um mundo está aberto o jogo mantém um lock no `session.lock` da pasta do
+// mundo (FileChannel.tryLock do Java: `fcntl` no Unix, LockFileEx no Windows) e o
+// arquivo é reescrito ao abrir o mundo. Isso funciona mesmo sem o latest.log.
+
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LockState {
+    Held,
+    Free,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LockEvidence {
+    // Pasta cujo session.lock está travado pelo jogo
+    Held(String),
+    // Todos os session.lock puderam ser verificados e nenhum está travado
+    NoneHeld,
+    // Não foi possível verificar os locks; pasta cujo session.lock foi modificado
+    // por último desde o início da sessão do jogo
+    RecentlyOpened(String),
+    Unknown,
+}
+
+#[cfg(unix)]
+pub fn lock_state(session_lock: &Path) -> LockState {
+    use std::os::unix::io::AsRawFd;
+
+    let Ok(file) = OpenOptions::new().read(true).open(session_lock) else {
+        return LockState::Unknown;
+    };
+
+    // F_GETLK só consulta: preenche `flock` com o lock conflitante de outro processo,
+    // ou devolve F_UNLCK se ninguém segura o arquivo
+    let mut flock: libc::flock = unsafe { std::mem::zeroed() };
+    flock.l_type = libc::F_WRLCK as libc::c_short;
+    flock.l_whence = libc::SEEK_SET as libc::c_short;
+    let result = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut flock) };
+
+    if result == -1 {
+        LockState::Unknown
+    } else if flock.l_type == libc::F_UNLCK as libc::c_short {
+        LockState::Free
+    } else {
+        LockState::Held
+    }
+}
+
+#[cfg(windows)]
+pub fn lock_state(session_lock: &Path) -> LockState {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    const ERROR_LOCK_VIOLATION: i32 = 33;
+
+    match OpenOptions::new()
+        .read(true)
+        .share_mode(0)
+        .open(session_lock)
+    {
+        Ok(_) => LockState::Free,
+        Err(e)
+            if matches!(
+                e.raw_os_error(),
+                Some(ERROR_SHARING_VIOLATION) | Some(ERROR_LOCK_VIOLATION)
+            ) =>
+        {
+            LockState::Held
+        }
+        Err(_) => LockState::Unknown,
+    }
+}
+
+// Verifica o session.lock de cada mundo da pasta saves. `session_started` é usado
+// apenas quando os locks não podem ser verificados: só conta um mundo cujo
+// session.lock foi modificado depois disso.
+pub fn inspect_saves(saves_dir: &Path, session_started: Option<SystemTime>) -> LockEvidence {
+    let Ok(entries) = fs::read_dir(saves_dir) else {
+        return LockEvidence::Unknown;
+    };
+
+    let mut all_checked = true;
+    let mut most_recent: Option<(SystemTime, String)> = None;
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let session_lock = entry.path().join("session.lock");
+        if !session_lock.is_file() {
+            continue;
+        }
+        let folder = entry.file_name().to_string_lossy().to_string();
+
+        match lock_state(&session_lock) {
+            LockState::Held => return LockEvidence::Held(folder),
+            LockState::Free => {}
+            LockState::Unknown => all_checked = false,
+        }
+
+        if let Ok(modified) = fs::metadata(&session_lock).and_then(|m| m.modified()) {
+            if most_recent
+                .as_ref()
+                .is_none_or(|(latest, _)| modified > *latest)
+            {
+                most_recent = Some((modified, folder));
+            }
+        }
+    }
+
+    if all_checked {
+        return LockEvidence::NoneHeld;
+    }
+
+    match (most_recent, session_started) {
+        (Some((modified, folder)), Some(started)) if modified >= started => {
+            LockEvidence::RecentlyOpened(folder)
+        }
+        _ => LockEvidence::Unknown,
+    }
+}
@@ -0,0 +1,401 @@
+// Cópia estável do mundo, feita antes de compactar.
+//
+// O jogo (ou o servidor) continua gravando os arquivos de região durante o backup, e
+// compactar direto da pasta do mundo pode juntar chunks de momentos diferentes do
+// `level.dat`. As pastas do grupo são copiadas para uma pasta temporária dentro da
+// pasta de backups do mundo (com reflink no btrfs e no xfs quando ela está no mesmo
+// sistema de arquivos, o que é quase instantâneo) e o tamanho e a data de
+// modificação de cada arquivo são comparados com os de antes da cópia. O que mudou
+// nesse meio-tempo é copiado de novo, até `MAX_PASSES` vezes, e o backup é feito da
+// cópia. Cada backup tem a sua pasta temporária, e as que sobraram de um backup
+// interrompido só são apagadas na abertura do app (`archive::remove_partials`).
+//
+// Um mundo fechado não muda durante o backup, então não é copiado: o lock do
+// `session.lock` (Java) ou do `db/LOCK` (Bedrock) diz se ele está aberto.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use tauri::AppHandle;
+use walkdir::WalkDir;
+
+use crate::archive::FILES_TO_IGNORE;
+use crate::session_lock::{self, LockState};
+use crate::utils::log_to_file_and_emit;
+use crate::world_group::WorldGroup;
+
+const MAX_PASSES: usize = 3;
+pub const SNAPSHOT_PREFIX: &str = ".auto-mine-backup-snapshot_";
+// Arquivos travados enquanto o mundo está aberto
+const WORLD_LOCKS: [&str; 2] = ["session.lock", "db/LOCK"];
+// Diferencia as pastas temporárias criadas no mesmo segundo
+static STAGING_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Snapshot {
+    // O mesmo grupo, com as pastas copiadas
+    pub group: WorldGroup,
+    pub files: usize,
+    // Arquivos copiados por reflink
+    pub cloned: usize,
+    // Arquivos que não puderam ser copiados
+    pub failed: usize,
+    // Arquivos que ainda estavam mudando depois da última passada
+    pub unstable: Vec<String>,
+    // Passadas de cópia feitas
+    pub passes: usize,
+}
+
+impl Snapshot {
+    pub fn is_consistent(&self) -> bool {
+        self.unstable.is_empty()
+    }
+
+    // Apaga a pasta temporária
+    pub async fn discard(self) {
+        let staging = self.group.root;
+        let _ = tauri::async_runtime::spawn_blocking(move || fs::remove_dir_all(staging)).await;
+    }
+}
+
+// Tamanho e data de modificação
+type Stamp = (u64, Option<SystemTime>);
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+// Se algum mundo do grupo está aberto. Sem nenhum lock que possa ser verificado, o
+// mundo é tratado como aberto.
+fn is_in_use(group: &WorldGroup) -> bool {
+    let states: Vec<LockState> = group
+        .dirs()
+        .iter()
+        .flat_map(|dir| WORLD_LOCKS.iter().map(move |lock| dir.join(lock)))
+        .filter(|lock| lock.is_file())
+        .map(|lock| session_lock::lock_state(&lock))
+        .collect();
+    states.is_empty() || states.iter().any(|state| *state != LockState::Free)
+}
+
+// Copia `group` para uma pasta temporária em `staging_root`, ou retorna `None` se o
+// mundo está fechado. Roda numa thread de bloqueio, como a compactação.
+pub async fn take(
+    group: &WorldGroup,
+    staging_root: &Path,
+    app_handle: &AppHandle,
+) -> io::Result<Option<Snapshot>> {
+    let group = group.clone();
+    let staging_root = staging_root.to_path_buf();
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        // Pastas de versões anteriores, que ficavam ao lado do mundo
+        remove_leftovers(&group.root);
+        if !is_in_use(&group) {
+            return Ok(None);
+        }
+        take_snapshot(&group, &staging_root, |source, target| {
+            copy_file(source, target).inspect_err(|e| {
+                log_to_file_and_emit(
+                    &app_handle,
+                    format!("[snapshot] Falha ao copiar {:?}: {}", source, e),
+                )
+            })
+        })
+        .map(Some)
+    })
+    .await
+    .map_err(|e| io::Error::other(format!("tarefa de cópia falhou: {}", e)))?
+}
+
+// Cria a pasta temporária de um backup. `create_dir` falha se a pasta já existe, então
+// dois backups nunca compartilham a mesma.
+fn create_staging(staging_root: &Path) -> io::Result<PathBuf> {
+    let staging = staging_root.join(format!(
+        "{}{}_{}_{}",
+        SNAPSHOT_PREFIX,
+        chrono::Local::now().format("%Y%m%d_%H%M%S"),
+        std::process::id(),
+        STAGING_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir(&staging)?;
+    Ok(staging)
+}
+
+// Copia o grupo em passadas com `copy`, que retorna se a cópia foi por reflink
+fn take_snapshot(
+    group: &WorldGroup,
+    staging_root: &Path,
+    mut copy: impl FnMut(&Path, &Path) -> io::Result<bool>,
+) -> io::Result<Snapshot> {
+    let staging = create_staging(staging_root)?;
+
+    let mut snapshot = Snapshot {
+        group: WorldGroup {
+            root: staging.clone(),
+            ..group.clone()
+        },
+        files: 0,
+        cloned: 0,
+        failed: 0,
+        unstable: Vec::new(),
+        passes: 0,
+    };
+    // Caminho relativo a `group.root` -> como o arquivo estava quando foi copiado
+    let mut copied: HashMap<PathBuf, Stamp> = HashMap::new();
+    let mut pending = changed_files(group, &staging, &mut copied);
+    let mut failed = HashSet::new();
+
+    for _ in 0..MAX_PASSES {
+        snapshot.passes += 1;
+        failed.clear();
+        for relative in &pending {
+            let source = group.root.join(relative);
+            let target = staging.join(relative);
+            let Some(before) = stamp(&source) else {
+                continue;
+            };
+            match copy(&source, &target) {
+                Ok(cloned) => {
+                    snapshot.cloned += cloned as usize;
+                    copied.insert(relative.clone(), before);
+                }
+                Err(_) => {
+                    failed.insert(relative.clone());
+                }
+            }
+        }
+
+        // Confere de novo tudo o que foi copiado: o que mudou, surgiu ou sumiu desde
+        // a cópia de cada arquivo
+        pending = changed_files(group, &staging, &mut copied);
+        if pending.is_empty() {
+            break;
+        }
+    }
+
+    snapshot.files = copied.len();
+    snapshot.failed = failed.len();
+    snapshot.unstable = pending
+        .iter()
+        .filter(|relative| !failed.contains(*relative))
+        .map(|relative| relative.to_string_lossy().to_string())
+        .collect();
+    Ok(snapshot)
+}
+
+// Arquivos do grupo que ainda não foram copiados ou mudaram desde a cópia. Os que
+// sumiram da origem saem também da cópia.
+fn changed_files(
+    group: &WorldGroup,
+    staging: &Path,
+    copied: &mut HashMap<PathBuf, Stamp>,
+) -> Vec<PathBuf> {
+    let mut changed = Vec::new();
+    let mut present = HashSet::new();
+    for dir in group.dirs() {
+        for entry in WalkDir::new(&dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+        {
+            let path = entry.path();
+            let relative = path.strip_prefix(&group.root).unwrap().to_path_buf();
+            if entry.file_type().is_dir() {
+                let _ = fs::create_dir_all(staging.join(&relative));
+                continue;
+            }
+            let ignored = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| FILES_TO_IGNORE.contains(&name));
+            if !path.is_file() || ignored {
+                continue;
+            }
+            if copied.get(&relative) != stamp(path).as_ref() {
+                changed.push(relative.clone());
+            }
+            present.insert(relative);
+        }
+    }
+
+    copied.retain(|relative, _| {
+        let keep = present.contains(relative);
+        if !keep {
+            let _ = fs::remove_file(staging.join(relative));
+        }
+        keep
+    });
+    changed
+}
+
+// Pastas temporárias que sobraram de um backup interrompido
+fn remove_leftovers(root: &Path) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(SNAPSHOT_PREFIX)
+        {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+// Copia um arquivo, por reflink quando o sistema de arquivos permite. Retorna se a
+// cópia foi por reflink.
+fn copy_file(source: &Path, target: &Path) -> io::Result<bool> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if clone_file(source, target) {
+        return Ok(true);
+    }
+    fs::copy(source, target).map(|_| false)
+}
+
+#[cfg(target_os = "linux")]
+fn clone_file(source: &Path, target: &Path) -> bool {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    let (Ok(source), Ok(target)) = (File::open(source), File::create(target)) else {
+        return false;
+    };
+    // FICLONE compartilha os blocos do arquivo original (btrfs, xfs)
+    let result = unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) };
+    result == 0
+}
+
+// No macOS, `fs::copy` já usa o clonefile do APFS
+#[cfg(not(target_os = "linux"))]
+fn clone_file(_source: &Path, _target: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "auto-mine-backup-snapshot-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("backups")).unwrap();
+        dir
+    }
+
+    fn world(dir: &Path) -> WorldGroup {
+        let world = dir.join("saves/world");
+        fs::create_dir_all(world.join("region")).unwrap();
+        fs::write(world.join("level.dat"), b"level").unwrap();
+        fs::write(world.join("region/r.0.0.mca"), b"region").unwrap();
+        WorldGroup::single("world", &world)
+    }
+
+    fn copies_of(copies: &[PathBuf], name: &str) -> usize {
+        copies.iter().filter(|path| path.ends_with(name)).count()
+    }
+
+    #[test]
+    fn unchanged_world_is_copied_in_one_pass() {
+        let dir = temp_dir("unchanged");
+        let group = world(&dir);
+        let mut copies = Vec::new();
+        let snapshot = take_snapshot(&group, &dir.join("backups"), |source, target| {
+            copies.push(source.to_path_buf());
+            copy_file(source, target)
+        })
+        .unwrap();
+
+        assert_eq!(snapshot.passes, 1);
+        assert_eq!(copies.len(), 2);
+        assert_eq!(snapshot.files, 2);
+        assert!(snapshot.is_consistent());
+        assert_eq!(
+            fs::read(snapshot.group.root.join("world/region/r.0.0.mca")).unwrap(),
+            b"region"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_changed_between_passes_is_copied_again() {
+        let dir = temp_dir("changed");
+        let group = world(&dir);
+        let region = group.root.join("world/region/r.0.0.mca");
+        let mut copies = Vec::new();
+        // O jogo grava a região logo depois da primeira cópia dela
+        let snapshot = take_snapshot(&group, &dir.join("backups"), |source, target| {
+            let cloned = copy_file(source, target)?;
+            copies.push(source.to_path_buf());
+            if source == region && copies_of(&copies, "r.0.0.mca") == 1 {
+                fs::write(source, b"region, gravada de novo").unwrap();
+            }
+            Ok(cloned)
+        })
+        .unwrap();
+
+        assert_eq!(snapshot.passes, 2);
+        assert_eq!(copies_of(&copies, "r.0.0.mca"), 2);
+        assert_eq!(copies_of(&copies, "level.dat"), 1);
+        assert!(snapshot.is_consistent());
+        assert_eq!(
+            fs::read(snapshot.group.root.join("world/region/r.0.0.mca")).unwrap(),
+            b"region, gravada de novo"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_changing_on_every_pass_is_reported_unstable() {
+        let dir = temp_dir("unstable");
+        let group = world(&dir);
+        let region = group.root.join("world/region/r.0.0.mca");
+        let mut size = 0;
+        let snapshot = take_snapshot(&group, &dir.join("backups"), |source, target| {
+            let cloned = copy_file(source, target)?;
+            if source == region {
+                size += 1;
+                fs::write(source, vec![0; size]).unwrap();
+            }
+            Ok(cloned)
+        })
+        .unwrap();
+
+        assert_eq!(snapshot.passes, MAX_PASSES);
+        assert_eq!(
+            snapshot.unstable,
+            vec![Path::new("world/region/r.0.0.mca")
+                .to_string_lossy()
+                .to_string()]
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn each_snapshot_gets_its_own_staging_folder() {
+        let dir = temp_dir("staging");
+        let group = world(&dir);
+        let first = take_snapshot(&group, &dir.join("backups"), copy_file).unwrap();
+        let second = take_snapshot(&group, &dir.join("backups"), copy_file).unwrap();
+
+        assert_ne!(first.group.root, second.group.root);
+        assert!(first.group.root.join("world/level.dat").is_file());
+        assert!(second.group.root.join("world/level.dat").is_file());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,15 +1,31 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use tauri::{AppHandle, Emitter, Wry};
-use tauri_plugin_shell::ShellExt;
-use tauri_plugin_store::Store;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tauri_plugin_store::{Store, StoreExt};
 
-use std::fs::File;
-use std::io::{Read, Write};
-use walkdir::WalkDir;
-use zip::write::SimpleFileOptions;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::enums::WorldStatus;
+use crate::archive::{self, ArchiveSettings};
+use crate::bedrock;
+use crate::catalog::{self, BackupRecord};
+use crate::detection::GameSession;
+use crate::enums::{ArchiveFormat, DetectionError, DetectionStrategy, WorldStatus};
+use crate::fingerprint;
+use crate::launchers;
+use crate::loaders;
+use crate::log_watcher::LogWatcher;
+use crate::paths;
+use crate::probes::{ProbeContext, ProcessProbe};
+use crate::rcon::RconClient;
+use crate::repository;
+use crate::restore;
+use crate::server::{self, DedicatedServer};
+use crate::session::GameActivity;
+use crate::snapshot;
+use crate::world_group::WorldGroup;
 
 pub fn log_to_file_and_emit<S: AsRef<str>>(app_handle: &AppHandle, msg: S) {
     use std::fs::{File, OpenOptions};
@@ -42,200 +58,291 @@ pub fn log_to_file_and_emit<S: AsRef<str>>(app_handle: &AppHandle, msg: S) {
         .unwrap();
 }
 
-pub async fn check_minecraft_running(app_handle: &AppHandle) -> WorldStatus {
-    let pwsh_script = r#"
-        $process = Get-CimInstance Win32_Process -Filter "Name = 'javaw.exe'" | Where-Object { $_.CommandLine -like "*net.minecraft.client.main.Main*" }
-		if (-not $process) {
-			Write-Output "STOPPED"
-			return
-		}
-
-		$commandLine = $process.CommandLine
-
-		if (-not ($commandLine -match '--gameDir "([^"]+)"')) {
-			if ($commandLine -match '--quickPlayPath "?([^"]+\.json)"?') {
-				$quickPlayPath = $matches[1]
-				$quickPlayJsonData = Get-Content -Path $quickPlayPath | ConvertFrom-Json
-				$worldId = $quickPlayJsonData[0].id
-
-				$isMultiplayer = $quickPlayJsonData[0].type -eq "multiplayer"
-
-				if ($isMultiplayer) {
-					Write-Output "MULTIPLAYER:$worldId"
-					return
-				}
-
-				if (-not $worldId) {
-					# Não achou o id no quickPlayPath (estranho), assuma Menu
-					Write-Output "NO_WORLDID:$quickPlayPath"
-					return
-				}	
-
-				Write-Output "WORLD:$worldId"
-				return
-			}
-		else {
-				# Não achou o gameDir (estranho), assuma Menu
-				Write-Output "NO_GAMEDIR:$commandLine"
-				return
-			}
-		}
-		else {
-			$gameDir = $matches[1]
-		}
-
-		$logPath = Join-Path -Path $gameDir -ChildPath "logs\latest.log"
-		if (-not (Test-Path $logPath)) {
-			# Achou o jogo mas não o log, assuma Menu
-			Write-Output "NO_LOG:$gameDir"
-			return
-		}
-
-		$patterns = "Connecting to", "Starting integrated server for world"
-		$lastMatch = Select-String -Path $logPath -Pattern $patterns | Select-Object -Last 1
-
-		if (-not $lastMatch) {
-			Write-Output "NO_MATCH"
-			return
-		}
-
-		if ($lastMatch.Pattern -eq "Connecting to") {
-			$worldName = ($lastMatch.Line -split ",")[0] -replace ".*Connecting to ", ""
-			Write-Output ("WORLD:" + $worldName.Trim())
-		}
-		elseif ($lastMatch.Pattern -eq "Starting integrated server for world") {
-			$worldName = $lastMatch.Line -replace ".*Starting integrated server for world '", "" -replace "'$", ""
-			Write-Output ("WORLD:" + $worldName.Trim())
-		}
-		else {
-			Write-Output "FALLBACK"
-		}
-    "#;
-
-    let shell = app_handle.shell();
-    let output_result = shell
-        .command("powershell")
-        .args(["-Command", pwsh_script])
-        .output()
-        .await;
-
-    match output_result {
-        Ok(output) => {
-            if !output.status.success() {
-                log_to_file_and_emit(
-                    &app_handle,
-                    format!(
-                        "[check_minecraft_running] Erro ao executar script PowerShell: {}",
-                        String::from_utf8_lossy(&output.stderr)
-                    ),
-                );
-                return WorldStatus::Stopped;
-            }
-
-            let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-
-            if stdout.starts_with("NO_GAMEDIR") {
-                let command_line = stdout.replace("NO_GAMEDIR:", "");
-                log_to_file_and_emit(&app_handle, format!("[check_minecraft_running] Jogo em execução, mas não foi possível determinar o gameDir. Linha de comando: {}", command_line));
-                return WorldStatus::MainMenu;
-            }
-
-            if stdout.starts_with("NO_WORLDID") {
-                let quickplay_path = stdout.replace("NO_WORLDID:", "");
-                log_to_file_and_emit(&app_handle, format!("[check_minecraft_running] Jogo em execução, mas não foi possível determinar o ID do mundo a partir do quickPlayPath: {}", quickplay_path));
-                return WorldStatus::MainMenu;
-            }
-
-            if stdout.starts_with("NO_WORLDDIR") {
-                let world_dir = stdout.replace("NO_WORLDDIR:", "");
-                log_to_file_and_emit(&app_handle, format!("[check_minecraft_running] Jogo em execução, mas não foi possível determinar o diretório do mundo em: {}", world_dir));
-                return WorldStatus::MainMenu;
-            }
-
-            if stdout.starts_with("NO_LOG") {
-                let game_dir = stdout.replace("NO_LOG:", "");
-                log_to_file_and_emit(&app_handle, format!("[check_minecraft_running] Jogo em execução, mas não foi possível encontrar o log em: {}", game_dir));
-                return WorldStatus::MainMenu;
-            }
-
-            if stdout.starts_with("NO_MATCH") {
-                log_to_file_and_emit(&app_handle, "[check_minecraft_running] Jogo em execução, mas nenhuma entrada de mundo encontrada no log.");
-                return WorldStatus::MainMenu;
-            }
-
-            if stdout.starts_with("STOPPED") {
-                return WorldStatus::Stopped;
-            }
+fn configured_list(app_handle: &AppHandle, key: &str) -> Vec<String> {
+    let Ok(store) = app_handle.store("settings.json") else {
+        return Vec::new();
+    };
+    store
+        .get(key)
+        .and_then(|values| {
+            values.as_array().map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.as_str().map(String::from))
+                    .collect()
+            })
+        })
+        .unwrap_or_default()
+}
 
-            if stdout.starts_with("MAIN_MENU") {
-                return WorldStatus::MainMenu;
-            }
+// Lista os clientes do Minecraft abertos. O erro indica que a detecção em si falhou
+// (não dá para saber se o jogo está aberto), diferente de uma lista vazia.
+pub async fn check_minecraft_running(
+    app_handle: &AppHandle,
+) -> Result<Vec<GameSession>, DetectionError> {
+    let launcher_dirs: Vec<PathBuf> = configured_list(app_handle, "launcher_dirs")
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    let instances =
+        tauri::async_runtime::spawn_blocking(move || launchers::discover_instances(&launcher_dirs))
+            .await
+            .unwrap_or_default();
+    let main_classes =
+        loaders::client_main_classes(&configured_list(app_handle, "client_main_classes"));
 
-            if stdout.starts_with("MULTIPLAYER:") {
-                let server_address = stdout.replace("MULTIPLAYER:", "").trim().to_string();
-                log_to_file_and_emit(
-                    &app_handle,
-                    format!(
-                        "[check_minecraft_running] Minecraft está em multiplayer no servidor: {}",
-                        server_address
-                    ),
-                );
-                return WorldStatus::Multiplayer(server_address);
-            }
+    let log_watcher = app_handle.state::<Arc<Mutex<LogWatcher>>>().inner().clone();
+    let strategy = app_handle
+        .store("settings.json")
+        .ok()
+        .and_then(|store| store.get("detection_strategy"))
+        .and_then(|strategy| strategy.as_str().map(DetectionStrategy::from_name))
+        .unwrap_or(DetectionStrategy::Log);
+    let minecraft_dir = app_handle
+        .store("settings.json")
+        .ok()
+        .and_then(|store| store.get("minecraft_dir"))
+        .and_then(|dir| dir.as_str().map(PathBuf::from));
 
-            if stdout.starts_with("WORLD:") {
-                let world_name = stdout.replace("WORLD:", "").trim().to_string();
-                return WorldStatus::InWorld(world_name);
-            }
+    let process_probe = app_handle.state::<Arc<dyn ProcessProbe>>().inner().clone();
+    let sessions = process_probe
+        .probe(ProbeContext {
+            instances,
+            main_classes,
+            strategy,
+            log_watcher,
+            minecraft_dir,
+        })
+        .await?;
 
-            // Fallback
+    for session in &sessions {
+        if let Some(instance) = &session.instance {
             log_to_file_and_emit(
-                &app_handle,
+                app_handle,
                 format!(
-                    "[check_minecraft_running] Status: Desconhecido (Saída: {})",
-                    stdout
+                    "[check_minecraft_running] [pid {}] Instância do {} em execução: {} (saves: {:?})",
+                    session.pid,
+                    instance.launcher,
+                    instance.name,
+                    instance.saves_dir()
                 ),
             );
-            return WorldStatus::Stopped;
         }
-        Err(e) => {
+
+        if let Some(confidence) = &session.confidence {
             log_to_file_and_emit(
-                &app_handle,
-                format!("[check_minecraft_running] Falha ao invocar shell: {}", e),
+                app_handle,
+                format!(
+                    "[check_minecraft_running] [pid {}] Resultado com confiança {:.2} (fontes: {})",
+                    session.pid,
+                    confidence.score,
+                    confidence.sources.join("+")
+                ),
             );
-            return WorldStatus::Stopped;
+        }
+
+        match &session.status {
+            Ok(WorldStatus::MainMenu) => log_to_file_and_emit(
+                app_handle,
+                format!(
+                    "[check_minecraft_running] [pid {}] Jogo em execução, mas nenhum mundo aberto.",
+                    session.pid
+                ),
+            ),
+            Ok(WorldStatus::Multiplayer(server_address)) => log_to_file_and_emit(
+                app_handle,
+                format!(
+                    "[check_minecraft_running] [pid {}] Minecraft está em multiplayer no servidor: {}",
+                    session.pid, server_address
+                ),
+            ),
+            Err(e) => log_to_file_and_emit(
+                app_handle,
+                format!(
+                    "[check_minecraft_running] [pid {}] Jogo em execução, mas {}",
+                    session.pid, e
+                ),
+            ),
+            _ => {}
         }
     }
+
+    Ok(sessions)
 }
 
 pub async fn backup_minecraft_world(
     app_handle: &AppHandle,
     store: &Arc<Store<Wry>>,
     world_name: String,
+    game_dir: Option<PathBuf>,
+) {
+    let minecraft_dir = store.get("minecraft_dir").unwrap_or_default();
+    let saves_dir = match paths::resolve_saves_dir(
+        game_dir.as_deref(),
+        minecraft_dir.as_str().map(Path::new),
+    ) {
+        Ok(saves_dir) => saves_dir,
+        Err(checked) => {
+            log_to_file_and_emit(
+                app_handle,
+                format!(
+                    "[backup_minecraft_world] Pasta saves não encontrada. Caminhos verificados: {:?}",
+                    checked
+                ),
+            );
+            return;
+        }
+    };
+    log_to_file_and_emit(
+        app_handle,
+        format!(
+            "[backup_minecraft_world] Usando pasta saves: {:?} ({})",
+            saves_dir.path, saves_dir.origin
+        ),
+    );
+
+    let mut saves_path = saves_dir.path.join(&world_name);
+    if !saves_path.exists() {
+        // Pode ser o nome de exibição do mundo (LevelName) em vez do nome da pasta
+        if let Some(world_folder) = paths::resolve_world_folder(&saves_dir.path, &world_name) {
+            saves_path = world_folder;
+        }
+    }
+    if !saves_path.exists() {
+        log_to_file_and_emit(
+            app_handle,
+            format!(
+                "[backup_minecraft_world] Diretório do mundo não encontrado: {:?}",
+                saves_path
+            ),
+        );
+        return;
+    }
+
+    backup_world_folder(
+        app_handle,
+        store,
+        &WorldGroup::single(&world_name, &saves_path),
+    )
+    .await;
+}
+
+// Espera cada jogo de `game_dirs` terminar o próximo salvamento do mundo (autosave ou
+// pausa), visto no latest.log, para que o backup pegue o mundo recém-gravado. Todos
+// os clientes são acompanhados juntos, então a espera da rodada inteira dura no
+// máximo `autosave_timeout` segundos (padrão: 300, o intervalo de autosave do jogo).
+// Desativado com `wait_for_autosave`.
+pub async fn wait_for_autosave(
+    app_handle: &AppHandle,
+    store: &Arc<Store<Wry>>,
+    game_dirs: &[PathBuf],
 ) {
+    let enabled = store
+        .get("wait_for_autosave")
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true);
+    if !enabled {
+        return;
+    }
+    let timeout = store
+        .get("autosave_timeout")
+        .and_then(|value| value.as_u64())
+        .unwrap_or(300);
+
+    let log_watcher = app_handle.state::<Arc<Mutex<LogWatcher>>>().inner().clone();
+    let saves = |log_path: &Path| {
+        let mut log_watcher = log_watcher.lock().unwrap();
+        log_watcher.poll(log_path).ok()?;
+        Some(log_watcher.saves(log_path))
+    };
+    // Log de cada jogo e quantos salvamentos ele tinha antes da espera
+    let mut waiting: Vec<(PathBuf, u64)> = game_dirs
+        .iter()
+        .map(|game_dir| game_dir.join("logs").join("latest.log"))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .filter_map(|log_path| Some((log_path.clone(), saves(&log_path)?)))
+        .collect();
+    if waiting.is_empty() {
+        return;
+    }
+
+    log_to_file_and_emit(
+        app_handle,
+        format!(
+            "[wait_for_autosave] Aguardando o próximo salvamento do mundo em {} jogo(s) (até {}s)...",
+            waiting.len(),
+            timeout
+        ),
+    );
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout);
+    while tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        waiting.retain(|(log_path, before)| {
+            let saved = saves(log_path).is_some_and(|after| after > *before);
+            if saved {
+                log_to_file_and_emit(
+                    app_handle,
+                    format!("[wait_for_autosave] Mundo salvo pelo jogo: {:?}", log_path),
+                );
+            }
+            !saved
+        });
+        if waiting.is_empty() {
+            return;
+        }
+    }
+    log_to_file_and_emit(
+        app_handle,
+        format!(
+            "[wait_for_autosave] Nenhum salvamento em {}s em {:?}; o backup será feito assim mesmo.",
+            timeout,
+            waiting
+                .iter()
+                .map(|(log_path, _)| log_path)
+                .collect::<Vec<_>>()
+        ),
+    );
+}
+
+// Um backup por vez em cada pasta de backup: o laço de saída e o de intervalo podem
+// pedir o backup do mesmo mundo ao mesmo tempo
+static BACKUP_LOCKS: Mutex<BTreeMap<PathBuf, Arc<tokio::sync::Mutex<()>>>> =
+    Mutex::new(BTreeMap::new());
+
+// Copia as pastas de um mundo para o destino configurado, aplicando o limite de
+// backups. O nome do grupo nomeia a pasta e os arquivos de backup; um grupo gera um
+// único arquivo e por isso conta como um backup no limite. Retorna se o backup foi
+// criado.
+pub async fn backup_world_folder(
+    app_handle: &AppHandle,
+    store: &Arc<Store<Wry>>,
+    group: &WorldGroup,
+) -> bool {
+    let world_name = &group.name;
     let selected_path = store.get("selected_path").unwrap_or_default();
     let max_backups = store.get("max_backups").unwrap_or_default();
 
     if selected_path.is_null() {
         log_to_file_and_emit(
-            &app_handle,
-            "[backup_minecraft_world] Caminho de backup não está configurado.",
+            app_handle,
+            "[backup_world_folder] Caminho de backup não está configurado.",
         );
-        return;
+        return false;
     }
 
     if max_backups.is_null() {
         log_to_file_and_emit(
-            &app_handle,
-            "[backup_minecraft_world] Número máximo de backups não está configurado.",
+            app_handle,
+            "[backup_world_folder] Número máximo de backups não está configurado.",
         );
-        return;
+        return false;
     }
 
     log_to_file_and_emit(
-        &app_handle,
+        app_handle,
         format!(
-            "[backup_minecraft_world] Iniciando backup do mundo: {} para o caminho: {}",
+            "[backup_world_folder] Iniciando backup do mundo: {} para o caminho: {}",
             world_name,
             selected_path.as_str().unwrap()
         ),
@@ -244,206 +351,697 @@ pub async fn backup_minecraft_world(
     let folder_name = format!("backup_{}", world_name);
     let backup_folder_path =
         std::path::Path::new(selected_path.as_str().unwrap()).join(&folder_name);
+    let world_lock = BACKUP_LOCKS
+        .lock()
+        .unwrap()
+        .entry(backup_folder_path.clone())
+        .or_default()
+        .clone();
+    let _world_guard = world_lock.lock().await;
     if !backup_folder_path.exists() {
         if let Err(e) = std::fs::create_dir_all(&backup_folder_path) {
             log_to_file_and_emit(
-                &app_handle,
+                app_handle,
                 format!(
-                    "[backup_minecraft_world] Falha ao criar diretório de backup: {:?} - {}",
+                    "[backup_world_folder] Falha ao criar diretório de backup: {:?} - {}",
                     backup_folder_path, e
                 ),
             );
-            return;
+            return false;
         }
     }
 
-    let files_in_folder: Vec<_> = std::fs::read_dir(&backup_folder_path)
-        .unwrap()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            if let Some(ext) = entry.path().extension() {
-                ext == "zip"
-            } else {
-                false
+    let mut backups = catalog::load(&backup_folder_path);
+
+    // Não repete o backup de um mundo que não mudou desde o último
+    // (`skip_unchanged_worlds`, padrão: ativado; `fingerprint_content_hashes` compara
+    // também o conteúdo dos arquivos)
+    let skip_unchanged = store
+        .get("skip_unchanged_worlds")
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true);
+    let content_hashes = store
+        .get("fingerprint_content_hashes")
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false);
+    let fingerprint = match fingerprint::compute(group, content_hashes).await {
+        Ok(fingerprint) => Some(fingerprint),
+        Err(e) => {
+            log_to_file_and_emit(
+                app_handle,
+                format!(
+                    "[backup_world_folder] Falha ao calcular a impressão digital do mundo: {}",
+                    e
+                ),
+            );
+            None
+        }
+    };
+    if let (true, Some(fingerprint), Some(latest)) = (skip_unchanged, &fingerprint, backups.last())
+    {
+        if fingerprint::is_unchanged_since(latest, fingerprint) {
+            log_to_file_and_emit(
+                app_handle,
+                format!(
+                    "[backup_world_folder] Backup ignorado: o mundo {} não mudou desde o último backup ({})",
+                    world_name, latest.name
+                ),
+            );
+            return false;
+        }
+    }
+
+    let game_running = app_handle.state::<GameActivity>().is_running();
+    let mut settings = ArchiveSettings::from_store(store, world_name, game_running);
+    if let Some(format) = group.format {
+        settings.format = format;
+    }
+    let created = chrono::Local::now();
+    let mut file_name = format!("backup_{}_{}", world_name, created.format("%Y%m%d_%H%M%S"));
+    if let Some(extension) = settings.format.extension() {
+        file_name = format!("{}.{}", file_name, extension);
+    }
+    let destination_path = backup_folder_path.join(&file_name);
+    let method = match settings.format {
+        ArchiveFormat::Zip | ArchiveFormat::McWorld => match settings.zip_level {
+            Some(level) => format!(" ({}, nível {})", settings.zip_method, level),
+            None => format!(" ({})", settings.zip_method),
+        },
+        _ => String::new(),
+    };
+    log_to_file_and_emit(
+        app_handle,
+        format!(
+            "[backup_world_folder] Formato: {}{}, {} thread(s){}",
+            settings.format,
+            method,
+            settings.threads,
+            if game_running { " (jogo aberto)" } else { "" }
+        ),
+    );
+
+    // Cópia estável do mundo aberto antes de compactar (`snapshot_before_backup`,
+    // padrão: ativado), feita na pasta de backups do mundo
+    let snapshot = if store
+        .get("snapshot_before_backup")
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true)
+    {
+        match snapshot::take(group, &backup_folder_path, app_handle).await {
+            Ok(None) => {
+                log_to_file_and_emit(
+                    app_handle,
+                    "[backup_world_folder] Mundo fechado; o backup será feito direto das pastas, sem cópia.",
+                );
+                None
             }
-        })
-        .collect();
-    let max_backups_value = max_backups.as_i64().unwrap_or(5) as usize;
-    if files_in_folder.len() >= max_backups_value {
-        let mut files_with_dates: Vec<(std::fs::DirEntry, std::time::SystemTime)> = files_in_folder
-            .into_iter()
-            .filter_map(|entry| {
-                if let Ok(metadata) = entry.metadata() {
-                    if let Ok(modified_time) = metadata.modified() {
-                        return Some((entry, modified_time));
+            Ok(Some(snapshot)) => {
+                let message = if snapshot.is_consistent() {
+                    format!(
+                        "[backup_world_folder] Cópia do mundo consistente: {} arquivo(s), {} por reflink",
+                        snapshot.files, snapshot.cloned
+                    )
+                } else {
+                    format!(
+                        "[backup_world_folder] Cópia do mundo inconsistente: {} arquivo(s) continuaram mudando durante a cópia: {:?}",
+                        snapshot.unstable.len(),
+                        snapshot.unstable
+                    )
+                };
+                log_to_file_and_emit(app_handle, message);
+                Some(snapshot)
+            }
+            Err(e) => {
+                log_to_file_and_emit(
+                    app_handle,
+                    format!(
+                        "[backup_world_folder] Falha ao copiar o mundo; o backup será feito direto das pastas: {}",
+                        e
+                    ),
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let source = snapshot.as_ref().map_or(group, |snapshot| &snapshot.group);
+    let written = archive::write_backup(source, &destination_path, &settings, app_handle).await;
+    let (inconsistent, snapshot_failed) = snapshot.as_ref().map_or((false, 0), |snapshot| {
+        (!snapshot.is_consistent(), snapshot.failed)
+    });
+    if let Some(snapshot) = snapshot {
+        snapshot.discard().await;
+    }
+
+    match written {
+        Ok(mut report) => {
+            report.skipped += snapshot_failed;
+            backups.push(BackupRecord {
+                name: file_name,
+                format: settings.format,
+                created: created.timestamp() as u64,
+                incomplete: report.skipped > 0,
+                inconsistent,
+                fingerprint,
+            });
+            // Os backups antigos só saem depois que o novo está completo e registrado;
+            // se o registro falhar, nada é apagado
+            match catalog::save(&backup_folder_path, &backups) {
+                Ok(()) => {
+                    let max_backups_value = max_backups.as_i64().unwrap_or(5).max(1) as usize;
+                    if prune_backups(
+                        app_handle,
+                        &backup_folder_path,
+                        &mut backups,
+                        max_backups_value,
+                    ) {
+                        if let Err(e) = catalog::save(&backup_folder_path, &backups) {
+                            log_to_file_and_emit(
+                                app_handle,
+                                format!(
+                                    "[backup_world_folder] Falha ao atualizar o registro de backups: {}",
+                                    e
+                                ),
+                            );
+                        }
                     }
                 }
-                None
-            })
-            .collect();
-        files_with_dates.sort_by_key(|&(_, modified_time)| modified_time);
-        let files_to_delete = files_with_dates.len() + 1 - max_backups_value;
-        for i in 0..files_to_delete {
-            let file_to_delete = &files_with_dates[i].0;
-            if let Err(e) = std::fs::remove_file(file_to_delete.path()) {
+                Err(e) => {
+                    log_to_file_and_emit(
+                        app_handle,
+                        format!(
+                            "[backup_world_folder] Falha ao atualizar o registro de backups; os backups antigos foram mantidos: {}",
+                            e
+                        ),
+                    );
+                }
+            }
+            if report.skipped > 0 {
                 log_to_file_and_emit(
-                    &app_handle,
+                    app_handle,
                     format!(
-                        "[backup_minecraft_world] Falha ao deletar backup antigo: {:?} - {}",
-                        file_to_delete.path(),
-                        e
+                        "[backup_world_folder] Backup incompleto: {:?} ({} arquivo(s) conferido(s), {} ficaram de fora)",
+                        destination_path, report.files, report.skipped
                     ),
                 );
             } else {
                 log_to_file_and_emit(
-                    &app_handle,
+                    app_handle,
                     format!(
-                        "[backup_minecraft_world] Backup antigo deletado: {:?}",
-                        file_to_delete.path()
+                        "[backup_world_folder] Backup concluído com sucesso: {:?} ({} arquivo(s) conferido(s))",
+                        destination_path, report.files
                     ),
                 );
             }
+            true
+        }
+        Err(e) => {
+            log_to_file_and_emit(
+                app_handle,
+                format!("[backup_world_folder] Falha ao criar backup: {}", e),
+            );
+            false
         }
     }
+}
 
-    let file_name = format!(
-        "backup_{}_{}.zip",
-        world_name,
-        chrono::Local::now().format("%Y%m%d_%H%M%S")
-    );
-    let destination_path = backup_folder_path.join(file_name);
+// Apaga os backups mais antigos até sobrarem `max_backups`. Retorna se algum registro
+// saiu da lista.
+fn prune_backups(
+    app_handle: &AppHandle,
+    backup_folder_path: &std::path::Path,
+    backups: &mut Vec<BackupRecord>,
+    max_backups: usize,
+) -> bool {
+    if backups.len() <= max_backups {
+        return false;
+    }
+    let backups_to_delete = backups.len() - max_backups;
+    for backup in backups.drain(..backups_to_delete) {
+        let backup_path = backup.path(backup_folder_path);
+        match catalog::remove_backup(backup_folder_path, &backup) {
+            Ok(None) => log_to_file_and_emit(
+                app_handle,
+                format!("[prune_backups] Backup antigo deletado: {:?}", backup_path),
+            ),
+            Ok(Some(e)) => log_to_file_and_emit(
+                app_handle,
+                format!(
+                    "[prune_backups] Backup antigo ilegível deletado: {:?} - {}",
+                    backup_path, e
+                ),
+            ),
+            Err(e) => log_to_file_and_emit(
+                app_handle,
+                format!(
+                    "[prune_backups] Falha ao deletar backup antigo: {:?} - {}",
+                    backup_path, e
+                ),
+            ),
+        }
+    }
+    true
+}
 
-    let dot_minecraft_path = dirs::home_dir()
-        .unwrap()
-        .join("AppData")
-        .join("Roaming")
-        .join(".minecraft");
+// Reconstrói um snapshot do repositório como pasta ou zip, escolhidos pelo usuário.
+// Bloqueia enquanto os diálogos estão abertos, como a restauração.
+pub fn export_snapshot_with_dialog(app_handle: &AppHandle) {
+    let mut pick_snapshot = app_handle
+        .dialog()
+        .file()
+        .set_title("Escolha o snapshot a exportar")
+        .add_filter("Snapshot", &["json"]);
+    if let Ok(store) = app_handle.store("settings.json") {
+        if let Some(selected_path) = store
+            .get("selected_path")
+            .and_then(|path| path.as_str().map(String::from))
+        {
+            pick_snapshot = pick_snapshot.set_directory(selected_path);
+        }
+    }
+    let Some(snapshot) = pick_snapshot
+        .blocking_pick_file()
+        .and_then(|path| path.into_path().ok())
+    else {
+        return;
+    };
+    let Some(destination) = app_handle
+        .dialog()
+        .file()
+        .set_title("Escolha onde salvar o snapshot exportado")
+        .blocking_pick_folder()
+        .and_then(|path| path.into_path().ok())
+    else {
+        return;
+    };
+    let as_zip = app_handle
+        .dialog()
+        .message("Exportar o snapshot como um arquivo zip ou como uma pasta?")
+        .title("Exportar snapshot")
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Zip".to_string(),
+            "Pasta".to_string(),
+        ))
+        .blocking_show();
 
-    let saves_path = dot_minecraft_path.join("saves").join(&world_name);
+    let name = snapshot
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = catalog::strip_extension(&name, ArchiveFormat::Repository);
+    let target = if as_zip {
+        destination.join(format!("{}.zip", name))
+    } else {
+        destination.join(name)
+    };
+    log_to_file_and_emit(
+        app_handle,
+        format!(
+            "[export_snapshot_with_dialog] Exportando {:?} para {:?}",
+            snapshot, target
+        ),
+    );
+    let exported = if target.exists() {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{:?} já existe", target),
+        ))
+    } else if as_zip {
+        repository::export_zip(&snapshot, &target)
+    } else {
+        repository::export_folder(&snapshot, &target)
+    };
+    let (message, kind) = match exported {
+        Ok(_) => (
+            format!("Snapshot exportado em:\n{}", target.display()),
+            MessageDialogKind::Info,
+        ),
+        Err(e) => {
+            log_to_file_and_emit(
+                app_handle,
+                format!("[export_snapshot_with_dialog] Falha ao exportar: {}", e),
+            );
+            (
+                format!("Falha ao exportar o snapshot: {}", e),
+                MessageDialogKind::Error,
+            )
+        }
+    };
+    app_handle
+        .dialog()
+        .message(message)
+        .title("Exportar snapshot")
+        .kind(kind)
+        .blocking_show();
+}
 
-    if !saves_path.exists() {
-        log_to_file_and_emit(
-            &app_handle,
-            format!(
-                "[backup_minecraft_world] Diretório do mundo não encontrado: {:?}",
-                saves_path
-            ),
-        );
+// Apaga os backups `.partial` deixados por um backup interrompido (o app fechado ou
+// o disco cheio no meio da escrita)
+pub fn remove_partial_backups(app_handle: &AppHandle, store: &Arc<Store<Wry>>) {
+    let Some(selected_path) = store
+        .get("selected_path")
+        .and_then(|path| path.as_str().map(String::from))
+    else {
         return;
+    };
+    for (path, unreadable) in archive::remove_partials(Path::new(&selected_path)) {
+        let message = match unreadable {
+            Some(e) => format!(
+                "[remove_partial_backups] Backup incompleto ilegível removido: {:?} - {}",
+                path, e
+            ),
+            None => format!(
+                "[remove_partial_backups] Backup incompleto removido: {:?}",
+                path
+            ),
+        };
+        log_to_file_and_emit(app_handle, message);
+    }
+}
+
+// Restaura um backup escolhido pelo usuário: primeiro o arquivo do backup (num
+// backup em pasta, o `auto-mine-backup.json` dentro dela), depois a pasta de destino
+// (a pasta saves do jogo ou a pasta do servidor). Bloqueia enquanto os diálogos
+// estão abertos, então não deve rodar na thread principal.
+pub fn restore_backup_with_dialog(app_handle: &AppHandle) {
+    let mut pick_archive = app_handle
+        .dialog()
+        .file()
+        .set_title("Escolha o backup a restaurar")
+        .add_filter("Backup", &["zip", "mcworld", "zst", "json"]);
+    if let Ok(store) = app_handle.store("settings.json") {
+        if let Some(selected_path) = store
+            .get("selected_path")
+            .and_then(|path| path.as_str().map(String::from))
+        {
+            pick_archive = pick_archive.set_directory(selected_path);
+        }
     }
+    let Some(archive) = pick_archive
+        .blocking_pick_file()
+        .and_then(|path| path.into_path().ok())
+    else {
+        return;
+    };
+    let Some(destination) = app_handle
+        .dialog()
+        .file()
+        .set_title("Escolha a pasta saves ou a pasta do servidor")
+        .blocking_pick_folder()
+        .and_then(|path| path.into_path().ok())
+    else {
+        return;
+    };
 
-    match zip_directory(&saves_path, &destination_path, &app_handle).await {
-        Ok(_) => {
+    log_to_file_and_emit(
+        app_handle,
+        format!(
+            "[restore_backup_with_dialog] Restaurando {:?} em {:?}",
+            archive, destination
+        ),
+    );
+    let (message, kind) = match restore::restore_backup(&archive, &destination) {
+        Ok(folders) => {
             log_to_file_and_emit(
-                &app_handle,
+                app_handle,
                 format!(
-                    "[backup_minecraft_world] Backup concluído com sucesso: {:?}",
-                    destination_path
+                    "[restore_backup_with_dialog] Pastas restauradas: {:?}",
+                    folders
                 ),
             );
-            store.set("last_backed_up_world", world_name);
+            (
+                format!(
+                    "Backup restaurado em:\n{}",
+                    folders
+                        .iter()
+                        .map(|folder| folder.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+                MessageDialogKind::Info,
+            )
         }
         Err(e) => {
             log_to_file_and_emit(
-                &app_handle,
-                format!("[backup_minecraft_world] Falha ao criar backup: {}", e),
+                app_handle,
+                format!("[restore_backup_with_dialog] Falha ao restaurar: {}", e),
             );
-            store.delete("last_backed_up_world");
+            (
+                format!("Falha ao restaurar o backup: {}", e),
+                MessageDialogKind::Error,
+            )
         }
-    }
+    };
+    app_handle
+        .dialog()
+        .message(message)
+        .title("Restaurar backup")
+        .kind(kind)
+        .blocking_show();
 }
 
-pub async fn zip_directory(
-    src_dir: &std::path::Path,
-    dst_file: &std::path::Path,
-    app_handle: &tauri::AppHandle,
-) -> zip::result::ZipResult<()> {
-    const FILES_TO_IGNORE: [&str; 2] = ["session.lock", "level.dat_old"];
+// Faz o backup dos mundos do Bedrock que mudaram desde o último backup. A data da
+// última modificação de cada mundo salvo fica em `bedrock_backups`. Com
+// `bedrock_mcworld` os arquivos são gerados como `.mcworld`.
+pub async fn backup_bedrock_worlds(app_handle: &AppHandle, store: &Arc<Store<Wry>>) {
+    let extra_dirs: Vec<PathBuf> = configured_list(app_handle, "bedrock_dirs")
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    let mcworld = store
+        .get("bedrock_mcworld")
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false);
+    let mut backed_up = store
+        .get("bedrock_backups")
+        .and_then(|value| value.as_object().cloned())
+        .unwrap_or_default();
+
+    // A busca dos mundos e das datas de modificação percorre todos os arquivos; roda
+    // numa thread de bloqueio, como a compactação
+    let worlds = match tauri::async_runtime::spawn_blocking(move || {
+        bedrock::discover_worlds(&extra_dirs)
+            .into_iter()
+            .map(|world| {
+                let modified = world.last_modified();
+                (world, modified)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    {
+        Ok(worlds) => worlds,
+        Err(e) => {
+            log_to_file_and_emit(
+                app_handle,
+                format!(
+                    "[backup_bedrock_worlds] Falha ao procurar os mundos do Bedrock: {}",
+                    e
+                ),
+            );
+            return;
+        }
+    };
 
-    let file = File::create(dst_file)?;
-    let mut zip = zip::ZipWriter::new(file);
+    for (world, modified) in worlds {
+        let key = world.dir.to_string_lossy().to_string();
+        let Some(modified) = modified else {
+            continue;
+        };
+        if backed_up.get(&key).and_then(|value| value.as_u64()) == Some(modified) {
+            continue;
+        }
 
-    let options = SimpleFileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o755);
+        log_to_file_and_emit(
+            app_handle,
+            format!(
+                "[backup_bedrock_worlds] Mundo do Bedrock modificado: {} ({:?}). Iniciando backup...",
+                world.name, world.dir
+            ),
+        );
+        let mut group = WorldGroup::single(&world.backup_name(), &world.dir);
+        if mcworld {
+            group.format = Some(ArchiveFormat::McWorld);
+        }
+        if backup_world_folder(app_handle, store, &group).await {
+            backed_up.insert(key, serde_json::json!(modified));
+            store.set(
+                "bedrock_backups",
+                serde_json::Value::Object(backed_up.clone()),
+            );
+        }
+    }
+}
 
-    for entry in WalkDir::new(src_dir) {
-        let entry = match entry {
-            Ok(e) => e,
+pub fn dedicated_servers(app_handle: &AppHandle) -> Vec<DedicatedServer> {
+    configured_list(app_handle, "server_dirs")
+        .into_iter()
+        .filter_map(|dir| match server::load_server(Path::new(&dir)) {
+            Ok(server) => Some(server),
             Err(e) => {
-                log_to_file_and_emit(
-                    app_handle,
-                    format!("[zip_directory] Falha ao acessar entrada: {}", e),
-                );
-                continue;
+                log_to_file_and_emit(app_handle, format!("[dedicated_servers] {}", e));
+                None
             }
-        };
-        let path = entry.path();
-        let name = path.strip_prefix(src_dir).unwrap();
+        })
+        .collect()
+}
 
-        // Ignorar arquivos da lista FILES_TO_IGNORE
-        if path.is_file() {
-            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                if FILES_TO_IGNORE.contains(&file_name) {
-                    log_to_file_and_emit(
-                        app_handle,
-                        format!("[zip_directory] Ignorando arquivo: {:?}", path),
-                    );
-                    continue;
-                }
-            }
-            if let Err(e) = zip.start_file(name.to_string_lossy(), options) {
-                log_to_file_and_emit(
-                    app_handle,
-                    format!(
-                        "[zip_directory] Falha ao iniciar arquivo no zip: {:?} - {}",
-                        path, e
-                    ),
-                );
-                continue;
-            }
-            let mut f = match File::open(path) {
-                Ok(file) => file,
-                Err(e) => {
-                    log_to_file_and_emit(
-                        app_handle,
-                        format!("[zip_directory] Falha ao abrir arquivo: {:?} - {}", path, e),
-                    );
-                    continue;
+const RCON_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+// `save-all flush` só responde depois de gravar todos os chunks no disco
+const RCON_FLUSH_TIMEOUT: Duration = Duration::from_secs(300);
+
+async fn rcon_command(
+    client: &mut RconClient,
+    command: &str,
+    limit: Duration,
+) -> Result<(), String> {
+    client
+        .command(command, limit)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("{}: {}", command, e))
+}
+
+// Faz o backup do mundo de um servidor dedicado. Com `coordinate` e o RCON
+// configurado, o salvamento automático é pausado durante o backup (`save-off` e
+// `save-all flush` antes, `save-on` depois) para que os arquivos fiquem consistentes.
+pub async fn backup_server_world(
+    app_handle: &AppHandle,
+    store: &Arc<Store<Wry>>,
+    server: &DedicatedServer,
+    coordinate: bool,
+) {
+    let world_dir = server.world_dir();
+    if !world_dir.exists() {
+        log_to_file_and_emit(
+            app_handle,
+            format!(
+                "[backup_server_world] Diretório do mundo do servidor não encontrado: {:?}",
+                world_dir
+            ),
+        );
+        return;
+    }
+
+    let mut rcon = None;
+    match (&server.rcon, coordinate) {
+        (Some(settings), true) => {
+            match RconClient::connect(&settings.address, &settings.password).await {
+                Ok(mut client) => {
+                    let prepared = async {
+                        rcon_command(&mut client, "save-off", RCON_COMMAND_TIMEOUT).await?;
+                        rcon_command(&mut client, "save-all flush", RCON_FLUSH_TIMEOUT).await
+                    }
+                    .await;
+                    match prepared {
+                        Ok(()) => log_to_file_and_emit(
+                            app_handle,
+                            format!(
+                                "[backup_server_world] Salvamento automático pausado em {}",
+                                settings.address
+                            ),
+                        ),
+                        Err(e) => log_to_file_and_emit(
+                            app_handle,
+                            format!(
+                                "[backup_server_world] Falha ao preparar o servidor via RCON ({}). O backup pode ficar inconsistente.",
+                                e
+                            ),
+                        ),
+                    }
+                    // Mesmo se o flush falhou, o save-off pode ter sido aplicado
+                    rcon = Some((client, settings));
                 }
-            };
-            let mut buffer = Vec::new();
-            if let Err(e) = f.read_to_end(&mut buffer) {
-                log_to_file_and_emit(
-                    app_handle,
-                    format!("[zip_directory] Falha ao ler arquivo: {:?} - {}", path, e),
-                );
-                continue;
-            }
-            if let Err(e) = zip.write_all(&buffer) {
-                log_to_file_and_emit(
+                Err(e) => log_to_file_and_emit(
                     app_handle,
                     format!(
-                        "[zip_directory] Falha ao escrever arquivo no zip: {:?} - {}",
-                        path, e
+                        "[backup_server_world] Falha ao conectar ao RCON em {}: {}. O backup pode ficar inconsistente.",
+                        settings.address, e
                     ),
-                );
-                continue;
+                ),
             }
-        } else if !name.as_os_str().is_empty() {
-            if let Err(e) = zip.add_directory(name.to_string_lossy(), options) {
+        }
+        (None, true) => log_to_file_and_emit(
+            app_handle,
+            format!(
+                "[backup_server_world] RCON desativado no server.properties de {:?}. O backup pode ficar inconsistente.",
+                server.dir
+            ),
+        ),
+        (_, false) => {}
+    }
+
+    let group = server.world_group();
+    if group.is_group() {
+        log_to_file_and_emit(
+            app_handle,
+            format!(
+                "[backup_server_world] Pastas de dimensão do mundo: {:?}",
+                group.folders
+            ),
+        );
+    }
+    backup_world_folder(app_handle, store, &group).await;
+
+    let Some((mut client, settings)) = rcon else {
+        return;
+    };
+    // O salvamento automático precisa voltar mesmo se a conexão caiu durante o backup
+    let mut result = rcon_command(&mut client, "save-on", RCON_COMMAND_TIMEOUT).await;
+    if result.is_err() {
+        result = match RconClient::connect(&settings.address, &settings.password).await {
+            Ok(mut client) => rcon_command(&mut client, "save-on", RCON_COMMAND_TIMEOUT).await,
+            Err(e) => Err(e.to_string()),
+        };
+    }
+    match result {
+        Ok(()) => log_to_file_and_emit(
+            app_handle,
+            format!(
+                "[backup_server_world] Salvamento automático reativado em {}",
+                settings.address
+            ),
+        ),
+        Err(e) => log_to_file_and_emit(
+            app_handle,
+            format!(
+                "[backup_server_world] Falha ao reativar o salvamento automático ({}). Execute save-on no servidor {:?}!",
+                e, server.dir
+            ),
+        ),
+    }
+}
+
+// Verifica os servidores dedicados configurados e faz o backup de saída dos que
+// pararam desde a última verificação. `running` guarda os servidores vistos em
+// execução.
+pub async fn check_dedicated_servers(
+    app_handle: &AppHandle,
+    store: &Arc<Store<Wry>>,
+    running: &mut HashSet<PathBuf>,
+) {
+    for server in dedicated_servers(app_handle) {
+        if server.is_running() {
+            if running.insert(server.dir.clone()) {
                 log_to_file_and_emit(
                     app_handle,
                     format!(
-                        "[zip_directory] Falha ao adicionar diretório ao zip: {:?} - {}",
-                        path, e
+                        "[check_dedicated_servers] Servidor em execução: {:?} (mundo: {})",
+                        server.dir, server.level_name
                     ),
                 );
-                continue;
             }
+        } else if running.remove(&server.dir) {
+            log_to_file_and_emit(
+                app_handle,
+                format!(
+                    "[check_dedicated_servers] Servidor parou: {:?}. Iniciando backup de saída...",
+                    server.dir
+                ),
+            );
+            // Parado, o servidor já salvou tudo: não há o que coordenar
+            backup_server_world(app_handle, store, &server, false).await;
         }
     }
-
-    zip.finish()?;
-    Ok(())
 }
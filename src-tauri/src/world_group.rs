@@ -0,0 +1,134 @@
+// Mundo lógico formado por uma ou mais pastas.
+//
+// No cliente e no servidor vanilla as dimensões ficam dentro da pasta do mundo
+// (`DIM-1`, `DIM1`). O Bukkit, o Spigot e o Paper separam cada dimensão numa pasta
+// irmã: `world`, `world_nether` e `world_the_end`. O grupo junta essas pastas num
+// único backup (um único arquivo, que conta como um backup no limite). Todo backup
+// leva um manifesto com as pastas que devem ser restauradas, porque o nome do
+// backup nem sempre é o nome da pasta (`server_<pasta>_<mundo>`,
+// `bedrock_<nome>_<id>`).
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::enums::ArchiveFormat;
+
+// Manifesto gravado na raiz dos arquivos de grupos
+pub const MANIFEST_NAME: &str = "auto-mine-backup.json";
+
+const DIMENSION_SUFFIXES: [&str; 2] = ["_nether", "_the_end"];
+
+#[derive(Debug, Clone)]
+pub struct WorldGroup {
+    // Nome usado na pasta e nos arquivos de backup
+    pub name: String,
+    // Pasta que contém as pastas do grupo (saves ou a pasta do servidor)
+    pub root: PathBuf,
+    pub folders: Vec<String>,
+    // Formato exigido pela origem (o Bedrock pode pedir `.mcworld`); sem ele vale o
+    // `archive_format` configurado
+    pub format: Option<ArchiveFormat>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupManifest {
+    pub world: String,
+    pub folders: Vec<String>,
+    // O conteúdo da única pasta está na raiz do arquivo, e não dentro de uma pasta
+    // com o nome dela (zip, `.mcworld` e tar.zst de um mundo só)
+    #[serde(default)]
+    pub at_root: bool,
+}
+
+impl WorldGroup {
+    pub fn single(name: &str, world_dir: &Path) -> WorldGroup {
+        WorldGroup {
+            name: name.to_string(),
+            root: world_dir
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            folders: vec![world_dir
+                .file_name()
+                .map(|folder| folder.to_string_lossy().to_string())
+                .unwrap_or_default()],
+            format: None,
+        }
+    }
+
+    // O mundo `level_name` e as pastas de dimensão do Bukkit que existirem ao lado
+    pub fn with_dimensions(name: &str, root: &Path, level_name: &str) -> WorldGroup {
+        let mut folders = vec![level_name.to_string()];
+        folders.extend(
+            DIMENSION_SUFFIXES
+                .iter()
+                .map(|suffix| format!("{}{}", level_name, suffix))
+                .filter(|folder| root.join(folder).is_dir()),
+        );
+        WorldGroup {
+            name: name.to_string(),
+            root: root.to_path_buf(),
+            folders,
+            format: None,
+        }
+    }
+
+    pub fn is_group(&self) -> bool {
+        self.folders.len() > 1
+    }
+
+    pub fn dirs(&self) -> Vec<PathBuf> {
+        self.folders
+            .iter()
+            .map(|folder| self.root.join(folder))
+            .collect()
+    }
+
+    pub fn manifest(&self, at_root: bool) -> GroupManifest {
+        GroupManifest {
+            world: self.name.clone(),
+            folders: self.folders.clone(),
+            at_root,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn groups_only_existing_dimension_folders() {
+        let root = std::env::temp_dir().join(format!(
+            "auto-mine-backup-world-group-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        for folder in ["world", "world_nether", "other_the_end"] {
+            fs::create_dir_all(root.join(folder)).unwrap();
+        }
+        let group = WorldGroup::with_dimensions("server_srv_world", &root, "world");
+        assert_eq!(group.folders, vec!["world", "world_nether"]);
+        assert!(group.is_group());
+        assert_eq!(group.dirs()[1], root.join("world_nether"));
+
+        let single = WorldGroup::single("world", &root.join("world"));
+        assert_eq!(single.root, root);
+        assert!(!single.is_group());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn manifests_without_at_root_are_folder_manifests() {
+        // Manifesto gravado antes de `at_root` existir
+        let manifest: GroupManifest = serde_json::from_str(
+            r#"{"world":"server_srv_world","folders":["world","world_nether"]}"#,
+        )
+        .unwrap();
+        assert!(!manifest.at_root);
+        assert_eq!(manifest.folders, vec!["world", "world_nether"]);
+    }
+}